@@ -16,13 +16,13 @@ fn parse_line(line: &str) -> Result<Vec<i32>> {
 fn prep_data(filepath: &str) -> Result<Vec<Vec<i32>>> {
     fs::read_to_string(filepath)?
         .lines()
-        .map(|line| parse_line(line))
+        .map(parse_line)
         .collect()
 }
 
 // part one methods
 
-fn is_ascending(data: &Vec<i32>) -> bool {
+fn is_ascending(data: &[i32]) -> bool {
     for i in 0..data.len() - 1 {
         if data[i] > data[i + 1] {
             return false;
@@ -32,7 +32,7 @@ fn is_ascending(data: &Vec<i32>) -> bool {
     true
 }
 
-fn is_descending(data: &Vec<i32>) -> bool {
+fn is_descending(data: &[i32]) -> bool {
     for i in 0..data.len() - 1 {
         if data[i] < data[i + 1] {
             return false;
@@ -42,7 +42,7 @@ fn is_descending(data: &Vec<i32>) -> bool {
     true
 }
 
-fn is_gradual(data: &Vec<i32>) -> bool {
+fn is_gradual(data: &[i32]) -> bool {
     for i in 0..data.len() - 1 {
         let distance = data[i] - data[i+1];
         if distance.abs() < 1 || distance.abs() > 3 {
@@ -68,7 +68,7 @@ fn part_one(filepath: &str) -> Result<i32> {
 
 // part two methods
 
-fn exclude_index<T: Copy>(data: &Vec<T>, index: usize) -> Vec<T> {
+fn exclude_index<T: Copy>(data: &[T], index: usize) -> Vec<T> {
     data.iter()
         .enumerate()
         .filter(move |&(i, _)| i != index)
@@ -76,7 +76,7 @@ fn exclude_index<T: Copy>(data: &Vec<T>, index: usize) -> Vec<T> {
         .collect()
 }
 
-fn make_safe(line: &Vec<i32>) -> bool {
+fn make_safe(line: &[i32]) -> bool {
     for i in 0..line.len() {
         let new_line = exclude_index(line, i);
         if (is_ascending(&new_line) || is_descending(&new_line)) && is_gradual(&new_line) {
@@ -92,10 +92,8 @@ fn part_two(filepath: &str) -> Result<i32> {
     let mut num_safe = 0;
 
     for line in data {
-        if !((is_ascending(&line) || is_descending(&line)) && is_gradual(&line)) {
-           if !make_safe(&line) {
-               continue;
-           }
+        if !((is_ascending(&line) || is_descending(&line)) && is_gradual(&line)) && !make_safe(&line) {
+            continue;
         }
         num_safe += 1;
     }
@@ -103,8 +101,171 @@ fn part_two(filepath: &str) -> Result<i32> {
     Ok(num_safe)
 }
 
+// diagnostic methods
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Violation {
+    DirectionFlip,
+    StepTooSmall,
+    StepTooLarge,
+}
+
+impl Violation {
+    fn name(&self) -> &'static str {
+        match self {
+            Violation::DirectionFlip => "direction flip",
+            Violation::StepTooSmall => "step too small",
+            Violation::StepTooLarge => "step too large",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Verdict {
+    Safe,
+    Dampened,
+    Unsafe,
+}
+
+impl Verdict {
+    fn name(&self) -> &'static str {
+        match self {
+            Verdict::Safe => "safe",
+            Verdict::Dampened => "dampened",
+            Verdict::Unsafe => "unsafe",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Offense {
+    first: usize,
+    second: usize,
+    violation: Violation,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Diagnosis {
+    line: usize,
+    verdict: Verdict,
+    offense: Option<Offense>,
+    removed: Option<usize>,
+}
+
+// Walks the report once and returns the first adjacent pair that breaks a rule.
+// The direction is fixed by the first pair, so a zero first step is reported as
+// too small rather than as a flip.
+fn find_offense(data: &[i32]) -> Option<Offense> {
+    let direction = match data {
+        [a, b, ..] => (b - a).signum(),
+        _ => return None,
+    };
+
+    for i in 0..data.len() - 1 {
+        let distance = data[i + 1] - data[i];
+        let violation = if distance == 0 {
+            Some(Violation::StepTooSmall)
+        } else if distance.signum() != direction {
+            Some(Violation::DirectionFlip)
+        } else if distance.abs() > 3 {
+            Some(Violation::StepTooLarge)
+        } else {
+            None
+        };
+
+        if let Some(violation) = violation {
+            return Some(Offense { first: i, second: i + 1, violation });
+        }
+    }
+
+    None
+}
+
+fn diagnose(line: usize, report: &[i32]) -> Diagnosis {
+    let offense = find_offense(report);
+    if offense.is_none() {
+        return Diagnosis { line, verdict: Verdict::Safe, offense, removed: None };
+    }
+
+    let removed = (0..report.len())
+        .find(|&i| find_offense(&exclude_index(report, i)).is_none());
+    let verdict = match removed {
+        Some(_) => Verdict::Dampened,
+        None => Verdict::Unsafe,
+    };
+
+    Diagnosis { line, verdict, offense, removed }
+}
+
+fn diagnose_data(data: &[Vec<i32>]) -> Vec<Diagnosis> {
+    data.iter()
+        .enumerate()
+        .map(|(i, report)| diagnose(i + 1, report))
+        .collect()
+}
+
+fn render_table(data: &[Vec<i32>], diagnoses: &[Diagnosis]) -> String {
+    let mut output = format!("{:>5}  {:<9} {:<8} {:<16} {:<8} report\n",
+                             "line", "verdict", "pair", "rule", "removed");
+
+    for (report, diagnosis) in data.iter().zip(diagnoses) {
+        let (pair, rule) = match diagnosis.offense {
+            Some(o) => (format!("{}-{}", o.first, o.second), o.violation.name()),
+            None => ("-".to_string(), "-"),
+        };
+        let removed = diagnosis.removed.map_or("-".to_string(), |i| i.to_string());
+        let report = report.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ");
+
+        output.push_str(&format!("{:>5}  {:<9} {:<8} {:<16} {:<8} {}\n",
+                                 diagnosis.line, diagnosis.verdict.name(), pair, rule, removed, report));
+    }
+
+    output
+}
+
+fn render_json(data: &[Vec<i32>], diagnoses: &[Diagnosis]) -> String {
+    let entries: Vec<String> = data.iter()
+        .zip(diagnoses)
+        .map(|(report, diagnosis)| {
+            let report = report.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",");
+            let offense = match diagnosis.offense {
+                Some(o) => format!("{{\"pair\":[{},{}],\"rule\":\"{}\"}}",
+                                   o.first, o.second, o.violation.name()),
+                None => "null".to_string(),
+            };
+            let removed = diagnosis.removed.map_or("null".to_string(), |i| i.to_string());
+
+            format!("  {{\"line\":{},\"report\":[{}],\"verdict\":\"{}\",\"offense\":{},\"removed\":{}}}",
+                    diagnosis.line, report, diagnosis.verdict.name(), offense, removed)
+        })
+        .collect();
+
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+fn diagnostics(filepath: &str, json: bool) -> Result<String> {
+    let data = prep_data(filepath)?;
+    let diagnoses = diagnose_data(&data);
+
+    match json {
+        true => Ok(render_json(&data, &diagnoses)),
+        false => Ok(render_table(&data, &diagnoses)),
+    }
+}
+
 fn main() {
     let filepath = "input.txt";
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("diagnose") {
+        let json = args.iter().any(|arg| arg == "--json");
+        match diagnostics(filepath, json) {
+            Ok(report) => print!("{}", report),
+            Err(e) => println!("Error: Could not diagnose reports. {}", e),
+        };
+        return;
+    }
+
 
     println!("Hello, Advent of Code 2024!");
 
@@ -167,4 +328,35 @@ mod tests {
         let filepath = "part_one_test_input.txt";
         assert!(part_two(filepath).unwrap() == 4);
     }
+
+    #[test]
+    fn test_find_offense() {
+        assert_eq!(find_offense(&[7, 6, 4, 2, 1]), None);
+        assert_eq!(find_offense(&[1, 2, 7, 8, 9]),
+                   Some(Offense { first: 1, second: 2, violation: Violation::StepTooLarge }));
+        assert_eq!(find_offense(&[1, 3, 2, 4, 5]),
+                   Some(Offense { first: 1, second: 2, violation: Violation::DirectionFlip }));
+        assert_eq!(find_offense(&[8, 6, 4, 4, 1]),
+                   Some(Offense { first: 2, second: 3, violation: Violation::StepTooSmall }));
+    }
+
+    #[test]
+    fn test_diagnose_data() {
+        let data = vec![vec![7, 6, 4, 2, 1],
+                        vec![1, 2, 7, 8, 9],
+                        vec![9, 7, 6, 2, 1],
+                        vec![1, 3, 2, 4, 5],
+                        vec![8, 6, 4, 4, 1],
+                        vec![1, 3, 6, 7, 9]];
+        let diagnoses = diagnose_data(&data);
+        let verdicts: Vec<Verdict> = diagnoses.iter().map(|d| d.verdict).collect();
+        assert_eq!(verdicts, vec![Verdict::Safe, Verdict::Unsafe, Verdict::Unsafe,
+                                  Verdict::Dampened, Verdict::Dampened, Verdict::Safe]);
+        assert_eq!(diagnoses[3].removed, Some(1));
+        assert_eq!(diagnoses[4].removed, Some(2));
+
+        let json = render_json(&data[3..4], &diagnoses[3..4]);
+        assert!(json.contains("\"line\":4,\"report\":[1,3,2,4,5],\"verdict\":\"dampened\""));
+        assert!(json.contains("\"offense\":{\"pair\":[1,2],\"rule\":\"direction flip\"},\"removed\":1"));
+    }
 }
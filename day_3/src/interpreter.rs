@@ -0,0 +1,526 @@
+use anyhow::{bail, Context, Result};
+use num_bigint::BigInt;
+use num_traits::{CheckedAdd, CheckedMul, CheckedSub, One, Zero};
+use std::fmt::{Debug, Display};
+use std::io::{BufReader, Read};
+use std::ops::RangeInclusive;
+
+// A single pass interpreter for the corrupted memory. Bytes are fed in one at a
+// time and the interpreter only ever holds on to the instruction it is currently
+// trying to match, so the input can be streamed rather than read up front.
+//
+// Which instructions exist is decided by an InstructionSet, each entry pairing
+// a name and argument grammar with what the instruction does when it runs.
+//
+// All arithmetic is checked. Arguments and the running total share one Number
+// type, so the same program can be run in i64 by default or widened to i128 or
+// a BigInt when the input is large enough to overflow.
+
+pub trait Number: Clone + Debug + Display + PartialEq + Zero + One + CheckedAdd + CheckedSub + CheckedMul + 'static {
+    fn parse(token: &str) -> Result<Self>;
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        $(impl Number for $t {
+            fn parse(token: &str) -> Result<Self> {
+                token
+                    .parse::<$t>()
+                    .with_context(|| format!("Could not parse '{}' to an integer", token))
+            }
+        })*
+    };
+}
+
+impl_number!(i32, i64, i128, BigInt);
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Arguments {
+    pub count: RangeInclusive<usize>,
+    pub digits: RangeInclusive<usize>,
+}
+
+impl Arguments {
+    pub fn none() -> Self {
+        Arguments { count: 0..=0, digits: 1..=1 }
+    }
+
+    pub fn exactly(count: usize, digits: RangeInclusive<usize>) -> Self {
+        Arguments { count: count..=count, digits }
+    }
+}
+
+// Returns None when the result does not fit in N.
+pub type Operation<N> = Box<dyn Fn(&[N]) -> Option<N>>;
+
+pub enum Action<N> {
+    Compute(Operation<N>),
+    Enable,
+    Disable,
+    // not used by the puzzle itself, kept for other instruction sets
+    #[allow(dead_code)]
+    Toggle,
+}
+
+pub struct Definition<N> {
+    pub name: String,
+    pub arguments: Arguments,
+    pub action: Action<N>,
+}
+
+pub struct InstructionSet<N> {
+    definitions: Vec<Definition<N>>,
+}
+
+impl<N: Number> Default for InstructionSet<N> {
+    fn default() -> Self {
+        InstructionSet { definitions: Vec::new() }
+    }
+}
+
+impl<N: Number> InstructionSet<N> {
+    pub fn new() -> Self {
+        InstructionSet::default()
+    }
+
+    // mul(X,Y) with one to three digit arguments, as described in part one
+    pub fn part_one() -> Self {
+        let mut set = InstructionSet::new();
+        set.register("mul", Arguments::exactly(2, 1..=3), Action::Compute(Box::new(|args: &[N]| args[0].checked_mul(&args[1]))))
+            .expect("mul is a valid definition");
+        set
+    }
+
+    // part one plus the do() and don't() conditionals
+    pub fn part_two() -> Self {
+        let mut set = InstructionSet::part_one();
+        set.register("do", Arguments::none(), Action::Enable)
+            .expect("do is a valid definition");
+        set.register("don't", Arguments::none(), Action::Disable)
+            .expect("don't is a valid definition");
+        set
+    }
+
+    pub fn register(&mut self, name: &str, arguments: Arguments, action: Action<N>) -> Result<&mut Self> {
+        if name.is_empty() || name.contains(['(', ')', ',']) {
+            bail!("Invalid instruction name '{}'", name);
+        }
+        if arguments.count.is_empty() || arguments.digits.is_empty() || *arguments.digits.start() == 0 {
+            bail!("Invalid argument grammar for instruction '{}'", name);
+        }
+        if self.definitions.iter().any(|d| d.name == name) {
+            bail!("Instruction '{}' is already registered", name);
+        }
+
+        self.definitions.push(Definition { name: name.to_string(), arguments, action });
+        Ok(self)
+    }
+
+    pub fn get(&self, opcode: usize) -> &Definition<N> {
+        &self.definitions[opcode]
+    }
+
+    pub fn describe(&self, instruction: &Instruction<N>) -> String {
+        let args: Vec<String> = instruction.args.iter().map(|a| a.to_string()).collect();
+        format!("{}({})", self.get(instruction.opcode).name, args.join(","))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Instruction<N> {
+    pub opcode: usize,
+    pub args: Vec<N>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Scan<T> {
+    Partial,
+    Complete(T),
+    Mismatch,
+}
+
+fn scan_definition<N: Number>(buf: &[u8], definition: &Definition<N>) -> Result<Scan<Vec<N>>> {
+    let name = definition.name.as_bytes();
+    let prefix_len = name.len() + 1;
+    let prefix = |i: usize| if i < name.len() { name[i] } else { b'(' };
+
+    for (i, &b) in buf.iter().take(prefix_len).enumerate() {
+        if b != prefix(i) {
+            return Ok(Scan::Mismatch);
+        }
+    }
+    if buf.len() < prefix_len {
+        return Ok(Scan::Partial);
+    }
+
+    let grammar = &definition.arguments;
+    let mut args = Vec::new();
+    let mut start = prefix_len;
+    let mut digits = 0;
+    for (i, &b) in buf.iter().enumerate().skip(prefix_len) {
+        match b {
+            b'0'..=b'9' if digits < *grammar.digits.end() => digits += 1,
+            b',' | b')' if grammar.digits.contains(&digits) => {
+                let token = std::str::from_utf8(&buf[start..i])?;
+                args.push(N::parse(token)?);
+                if b == b',' && args.len() >= *grammar.count.end() {
+                    return Ok(Scan::Mismatch);
+                }
+                if b == b')' {
+                    return match grammar.count.contains(&args.len()) && i == buf.len() - 1 {
+                        true => Ok(Scan::Complete(args)),
+                        false => Ok(Scan::Mismatch),
+                    };
+                }
+                start = i + 1;
+                digits = 0;
+            },
+            b')' if digits == 0 && args.is_empty() && grammar.count.contains(&0) => {
+                return match i == buf.len() - 1 {
+                    true => Ok(Scan::Complete(args)),
+                    false => Ok(Scan::Mismatch),
+                };
+            },
+            _ => return Ok(Scan::Mismatch),
+        }
+    }
+
+    Ok(Scan::Partial)
+}
+
+fn scan<N: Number>(buf: &[u8], set: &InstructionSet<N>) -> Result<Scan<Instruction<N>>> {
+    let mut result = Scan::Mismatch;
+    for (opcode, definition) in set.definitions.iter().enumerate() {
+        match scan_definition(buf, definition)? {
+            Scan::Complete(args) => return Ok(Scan::Complete(Instruction { opcode, args })),
+            Scan::Partial => result = Scan::Partial,
+            Scan::Mismatch => (),
+        }
+    }
+
+    Ok(result)
+}
+
+// One executed instruction as seen by the trace. `enabled` is the state the
+// interpreter was in when it ran, so a Compute step with `enabled == false` was
+// suppressed and did not add to `total`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Step<N> {
+    pub offset: usize,
+    pub len: usize,
+    pub instruction: Instruction<N>,
+    pub enabled: bool,
+    pub total: N,
+}
+
+pub struct Interpreter<N> {
+    set: InstructionSet<N>,
+    enabled: bool,
+    sum: N,
+    pending: Vec<u8>,
+    offset: usize,
+    trace: Option<Vec<Step<N>>>,
+}
+
+impl<N: Number> Interpreter<N> {
+    pub fn new(set: InstructionSet<N>) -> Self {
+        Interpreter {
+            set,
+            enabled: true,
+            sum: N::zero(),
+            pending: Vec::new(),
+            offset: 0,
+            trace: None,
+        }
+    }
+
+    pub fn with_trace(set: InstructionSet<N>) -> Self {
+        Interpreter {
+            trace: Some(Vec::new()),
+            ..Interpreter::new(set)
+        }
+    }
+
+    pub fn sum(&self) -> &N {
+        &self.sum
+    }
+
+    pub fn set(&self) -> &InstructionSet<N> {
+        &self.set
+    }
+
+    pub fn steps(&self) -> &[Step<N>] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    fn execute(&mut self, instruction: Instruction<N>) -> Result<()> {
+        let enabled = self.enabled;
+        let offset = self.offset - self.pending.len();
+        match &self.set.get(instruction.opcode).action {
+            Action::Compute(f) if self.enabled => {
+                let value = f(&instruction.args).with_context(|| format!(
+                    "Overflow evaluating {} at byte offset {}", self.set.describe(&instruction), offset))?;
+                self.sum = self.sum.checked_add(&value).with_context(|| format!(
+                    "Overflow adding {} from {} to the running total {} at byte offset {}",
+                    value, self.set.describe(&instruction), self.sum, offset))?;
+            },
+            Action::Compute(_) => (),
+            Action::Enable => self.enabled = true,
+            Action::Disable => self.enabled = false,
+            Action::Toggle => self.enabled = !self.enabled,
+        }
+
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(Step {
+                offset,
+                len: self.pending.len(),
+                instruction,
+                enabled,
+                total: self.sum.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Result<()> {
+        self.pending.push(byte);
+        self.offset += 1;
+
+        // On a mismatch the bytes after the failed start may still begin a
+        // valid instruction, so keep dropping the front and rescanning.
+        while !self.pending.is_empty() {
+            match scan(&self.pending, &self.set)? {
+                Scan::Partial => break,
+                Scan::Complete(instruction) => {
+                    self.execute(instruction)?;
+                    self.pending.clear();
+                },
+                Scan::Mismatch => {
+                    self.pending.remove(0);
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn feed_all<N: Number, R: Read>(interpreter: &mut Interpreter<N>, reader: R) -> Result<()> {
+    for byte in BufReader::new(reader).bytes() {
+        interpreter.feed(byte.with_context(|| "Could not read input")?)?;
+    }
+
+    Ok(())
+}
+
+pub fn run<N: Number, R: Read>(reader: R, set: InstructionSet<N>) -> Result<N> {
+    let mut interpreter = Interpreter::new(set);
+    feed_all(&mut interpreter, reader)?;
+
+    Ok(interpreter.sum)
+}
+
+pub fn trace<N: Number, R: Read>(reader: R, set: InstructionSet<N>) -> Result<Interpreter<N>> {
+    let mut interpreter = Interpreter::with_trace(set);
+    feed_all(&mut interpreter, reader)?;
+
+    Ok(interpreter)
+}
+
+// trace rendering
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const GREEN: &str = "\x1b[1;32m";
+const RED: &str = "\x1b[1;31m";
+const CYAN: &str = "\x1b[1;36m";
+
+fn status<N: Number>(set: &InstructionSet<N>, step: &Step<N>) -> &'static str {
+    match set.get(step.instruction.opcode).action {
+        Action::Compute(_) if step.enabled => "enabled",
+        Action::Compute(_) => "suppressed",
+        _ => "control",
+    }
+}
+
+pub fn render_trace<N: Number>(interpreter: &Interpreter<N>) -> String {
+    let mut output = format!("{:>8}  {:<20} {:<10} {:>12}\n", "offset", "instruction", "status", "total");
+    for step in interpreter.steps() {
+        output.push_str(&format!("{:>8}  {:<20} {:<10} {:>12}\n",
+                                 step.offset,
+                                 interpreter.set().describe(&step.instruction),
+                                 status(interpreter.set(), step),
+                                 step.total));
+    }
+
+    output
+}
+
+// Prints the input with the noise dimmed and every recognized instruction
+// colored by what happened to it: green counted, red suppressed, cyan control.
+pub fn render_highlight<N: Number>(input: &[u8], interpreter: &Interpreter<N>) -> String {
+    let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+    let mut output = String::new();
+    let mut cursor = 0;
+
+    for step in interpreter.steps() {
+        let color = match status(interpreter.set(), step) {
+            "enabled" => GREEN,
+            "suppressed" => RED,
+            _ => CYAN,
+        };
+        output.push_str(&format!("{}{}{}", DIM, text(&input[cursor..step.offset]), RESET));
+        output.push_str(&format!("{}{}{}", color, text(&input[step.offset..step.offset + step.len]), RESET));
+        cursor = step.offset + step.len;
+    }
+    output.push_str(&format!("{}{}{}", DIM, text(&input[cursor..]), RESET));
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product<N: Number>(args: &[N]) -> Option<N> {
+        args.iter().try_fold(N::one(), |acc, x| acc.checked_mul(x))
+    }
+
+    fn wide_set<N: Number>() -> InstructionSet<N> {
+        let mut set = InstructionSet::part_two();
+        set.register("product", Arguments { count: 1..=5, digits: 1..=10 }, Action::Compute(Box::new(product)))
+            .unwrap();
+        set
+    }
+
+    fn scan_text(text: &str, set: &InstructionSet<i64>) -> Scan<Instruction<i64>> {
+        scan(text.as_bytes(), set).unwrap()
+    }
+
+    #[test]
+    fn test_scan() {
+        let set = InstructionSet::<i64>::part_two();
+        assert_eq!(scan_text("mu", &set), Scan::Partial);
+        assert_eq!(scan_text("mul(12,", &set), Scan::Partial);
+        assert_eq!(scan_text("mul(12,3)", &set), Scan::Complete(Instruction { opcode: 0, args: vec![12, 3] }));
+        assert_eq!(scan_text("mul(,3)", &set), Scan::Mismatch);
+        assert_eq!(scan_text("mul(1234", &set), Scan::Mismatch);
+        assert_eq!(scan_text("mul(1,2,", &set), Scan::Mismatch);
+        assert_eq!(scan_text("mul[", &set), Scan::Mismatch);
+        assert_eq!(scan_text("don", &set), Scan::Partial);
+        assert_eq!(scan_text("do()", &set), Scan::Complete(Instruction { opcode: 1, args: vec![] }));
+        assert_eq!(scan_text("don't()", &set), Scan::Complete(Instruction { opcode: 2, args: vec![] }));
+        assert_eq!(scan_text("do(1)", &set), Scan::Mismatch);
+    }
+
+    #[test]
+    fn test_run_examples() {
+        let part_one = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
+        assert_eq!(run(part_one.as_bytes(), InstructionSet::<i64>::part_one()).unwrap(), 161);
+
+        let part_two = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        assert_eq!(run(part_two.as_bytes(), InstructionSet::<i64>::part_one()).unwrap(), 161);
+        assert_eq!(run(part_two.as_bytes(), InstructionSet::<i64>::part_two()).unwrap(), 48);
+    }
+
+    #[test]
+    fn test_run_edge_cases() {
+        let set = InstructionSet::<i64>::part_two;
+        // a trailing don't() with no closing do() disables the rest of the input
+        assert_eq!(run("mul(2,3)don't()mul(4,5)".as_bytes(), set()).unwrap(), 6);
+        // overlapping starts are rescanned instead of being skipped
+        assert_eq!(run("mumul(2,3)mul(mul(4,5)".as_bytes(), set()).unwrap(), 26);
+        // deleting a span must not splice the text around it into a new instruction
+        assert_eq!(run("mul(2,don't()xdo()3)".as_bytes(), set()).unwrap(), 0);
+        // arguments longer than three digits are not instructions
+        assert_eq!(run("mul(1000,2)mul(999,2)".as_bytes(), set()).unwrap(), 1998);
+    }
+
+    #[test]
+    fn test_trace() {
+        let input = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let interpreter = trace(input.as_bytes(), InstructionSet::<i64>::part_two()).unwrap();
+        let steps: Vec<(usize, usize, String, bool, i64)> = interpreter
+            .steps()
+            .iter()
+            .map(|s| (s.offset, s.len, interpreter.set().describe(&s.instruction), s.enabled, s.total))
+            .collect();
+
+        assert_eq!(steps, vec![
+            (1, 8, "mul(2,4)".to_string(), true, 8),
+            (20, 7, "don't()".to_string(), true, 8),
+            (28, 8, "mul(5,5)".to_string(), false, 8),
+            (48, 9, "mul(11,8)".to_string(), false, 8),
+            (59, 4, "do()".to_string(), false, 8),
+            (64, 8, "mul(8,5)".to_string(), true, 48),
+        ]);
+        for step in interpreter.steps() {
+            let text = &input[step.offset..step.offset + step.len];
+            assert_eq!(text, interpreter.set().describe(&step.instruction));
+        }
+
+        let table = render_trace(&interpreter);
+        assert_eq!(table.lines().count(), 7);
+        assert!(table.contains("mul(5,5)             suppressed"));
+
+        let highlight = render_highlight(input.as_bytes(), &interpreter);
+        assert!(highlight.contains(&format!("{}mul(2,4){}", GREEN, RESET)));
+        assert!(highlight.contains(&format!("{}mul(11,8){}", RED, RESET)));
+        assert!(highlight.contains(&format!("{}do(){}", CYAN, RESET)));
+    }
+
+    #[test]
+    fn test_register() {
+        let mut set = InstructionSet::<i64>::part_one();
+        set.register("add", Arguments::exactly(2, 1..=3), Action::Compute(Box::new(|args| args[0].checked_add(args[1]))))
+            .unwrap()
+            .register("sub", Arguments::exactly(2, 1..=3), Action::Compute(Box::new(|args| args[0].checked_sub(args[1]))))
+            .unwrap()
+            .register("product", Arguments { count: 1..=4, digits: 1..=2 }, Action::Compute(Box::new(product)))
+            .unwrap()
+            .register("flip", Arguments::none(), Action::Toggle)
+            .unwrap();
+
+        assert!(set.register("mul", Arguments::none(), Action::Enable).is_err());
+        assert!(set.register("bad(", Arguments::none(), Action::Enable).is_err());
+        assert!(set.register("empty", Arguments::exactly(1, 0..=3), Action::Enable).is_err());
+
+        let input = "add(1,2)sub(10,4)product(2,3,4)flip()mul(5,5)product(1,2,3,4,5)flip()product(7)";
+        assert_eq!(run(input.as_bytes(), set).unwrap(), 3 + 6 + 24 + 7);
+    }
+
+    #[test]
+    fn test_overflow_boundary() {
+        // 46340^2 is the largest square that fits in an i32
+        let fits = "product(46340,46340)product(88047)";
+        assert_eq!(run(fits.as_bytes(), wide_set::<i32>()).unwrap(), i32::MAX);
+
+        let error = run("product(46341,46341)".as_bytes(), wide_set::<i32>()).unwrap_err();
+        assert_eq!(error.to_string(), "Overflow evaluating product(46341,46341) at byte offset 0");
+
+        let error = run(format!("{}product(1)", fits).as_bytes(), wide_set::<i32>()).unwrap_err();
+        assert_eq!(error.to_string(),
+                   "Overflow adding 1 from product(1) to the running total 2147483647 at byte offset 34");
+
+        // an argument that cannot be represented at all is an error rather than noise
+        assert!(run("product(2147483648)".as_bytes(), wide_set::<i32>()).is_err());
+
+        // suppressed instructions are never evaluated, so they cannot overflow
+        assert_eq!(run("don't()product(46341,46341)".as_bytes(), wide_set::<i32>()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_wider_accumulators() {
+        let input = "product(2147483647,2147483647,2147483647)";
+        assert!(run(input.as_bytes(), wide_set::<i64>()).is_err());
+
+        let expected = (i32::MAX as i128).pow(3);
+        assert_eq!(run(input.as_bytes(), wide_set::<i128>()).unwrap(), expected);
+
+        let input = format!("{}product({})", input, ["2147483647"; 5].join(","));
+        let expected = BigInt::from(expected) + BigInt::from(i32::MAX).pow(5);
+        assert!(run(input.as_bytes(), wide_set::<i128>()).is_err());
+        assert_eq!(run(input.as_bytes(), wide_set::<BigInt>()).unwrap(), expected);
+    }
+}
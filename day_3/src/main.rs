@@ -1,27 +1,18 @@
+mod interpreter;
+
+use interpreter::{InstructionSet, Number};
 use anyhow::Result;
+use num_bigint::BigInt;
 use std::time::Instant;
-use regex::Regex;
-use std::fs;
+use std::fs::{self, File};
 
 // part one functions
 
-fn get_sum(input: &str) -> Result<i32> {
-    let re = match Regex::new(r"mul\((\d+),(\d+)\)") {
-        Ok(re) => re,
-        Err(e) => return Err(anyhow::anyhow!("Could not create regex: {}", e)),
-    };
-
-    let mut sum = 0;
-
-    for (_, [first, second]) in re.captures_iter(input).map(|c| c.extract()) {
-        let result = first.parse::<i32>()? * second.parse::<i32>()?;
-        sum += result;
-    }
-
-    Ok(sum)
+fn get_sum<N: Number>(input: &str) -> Result<N> {
+    interpreter::run(input.as_bytes(), InstructionSet::part_one())
 }
 
-fn part_one(filepath: &str) -> Result<i32> {
+fn part_one<N: Number>(filepath: &str) -> Result<N> {
     let data: String = fs::read_to_string(filepath)?;
     let ans = get_sum(&data)?;
     
@@ -30,23 +21,57 @@ fn part_one(filepath: &str) -> Result<i32> {
 
 // part two functions
 
-fn part_two(filepath: &str) -> Result<i32> {
-    let data: String = fs::read_to_string(filepath)?;
-    let re = Regex::new(r"don't\(\)[\s\S]*?do\(\)")?;
-    let data = re.replace_all(&data, "");
-    let ans = get_sum(&data)?;
+fn part_two<N: Number>(filepath: &str) -> Result<N> {
+    let file = File::open(filepath)?;
+    let ans = interpreter::run(file, InstructionSet::part_two())?;
 
     Ok(ans)
 }
 
+// trace functions
+
+fn show_trace(filepath: &str, part_one: bool) -> Result<String> {
+    let data = fs::read(filepath)?;
+    let set = match part_one {
+        true => InstructionSet::<i64>::part_one(),
+        false => InstructionSet::<i64>::part_two(),
+    };
+    let interpreter = interpreter::trace(data.as_slice(), set)?;
+
+    Ok(format!("{}\n{}\n\nTotal: {}",
+               interpreter::render_trace(&interpreter),
+               interpreter::render_highlight(&data, &interpreter),
+               interpreter.sum()))
+}
+
 fn main() {
     let filepath = "input.txt";
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("trace") {
+        let part_one = args.iter().any(|arg| arg == "--part-one");
+        match show_trace(filepath, part_one) {
+            Ok(trace) => println!("{}", trace),
+            Err(e) => println!("Error: Could not trace the program. {}", e),
+        };
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--bigint") {
+        solve::<BigInt>(filepath);
+    } else if args.iter().any(|arg| arg == "--i128") {
+        solve::<i128>(filepath);
+    } else {
+        solve::<i64>(filepath);
+    }
+}
 
+fn solve<N: Number>(filepath: &str) {
     println!("Hello, Advent of Code 2024!");
 
     let now = Instant::now();
 
-    match part_one(filepath) {
+    match part_one::<N>(filepath) {
         Ok(answer) => println!("Day 3, Part 1 answer: {}", answer),
         Err(e) => println!("Error: Could not calculate part one answer. {}", e),
     };
@@ -54,7 +79,7 @@ fn main() {
     println!("Day 3, Part 1 time elapsed {:.2?}", now.elapsed());
     let now = Instant::now();
 
-    match part_two(filepath) {
+    match part_two::<N>(filepath) {
         Ok(answer) => println!("Day 3, Part 2 answer: {}", answer),
         Err(e) => println!("Error: Could not calculate part two answer. {}", e),
     };
@@ -73,6 +98,6 @@ mod tests {
     #[test]
     fn test_part_one() {
         let filepath = "part_one_test_input.txt";
-        assert_eq!(part_one(filepath).unwrap(), 2652);
+        assert_eq!(part_one::<i64>(filepath).unwrap(), 2652);
     }
 }
@@ -0,0 +1,180 @@
+use crate::search::{Direction, Match, Search};
+use aho_corasick::AhoCorasick;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+// Multi-word search that reads every row, column and diagonal of the grid as a
+// string and runs a single Aho-Corasick automaton over all of them. Reversed
+// words are added as extra patterns so each line only has to be read one way,
+// and every hit is mapped back to a grid position and direction afterwards.
+
+struct Line {
+    // direction the line is read in
+    direction: Direction,
+    text: String,
+    // grid coordinate of the character starting at each byte offset
+    coords: Vec<Option<(usize, usize)>>,
+}
+
+impl Line {
+    fn new(direction: Direction, cells: impl Iterator<Item = (usize, usize)>, grid: &[Vec<char>]) -> Self {
+        let mut text = String::new();
+        let mut coords = Vec::new();
+
+        for (r, c) in cells {
+            let ch = grid[r][c];
+            text.push(ch);
+            coords.push(Some((r, c)));
+            coords.extend(std::iter::repeat_n(None, ch.len_utf8() - 1));
+        }
+
+        Line { direction, text, coords }
+    }
+
+    // coordinate of the last character before byte offset `end`
+    fn last_coord(&self, end: usize) -> (usize, usize) {
+        self.coords[..end]
+            .iter()
+            .rev()
+            .find_map(|coord| *coord)
+            .expect("a match is never empty")
+    }
+}
+
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::North     => Direction::South,
+        Direction::NorthEast => Direction::SouthWest,
+        Direction::East      => Direction::West,
+        Direction::SouthEast => Direction::NorthWest,
+        Direction::South     => Direction::North,
+        Direction::SouthWest => Direction::NorthEast,
+        Direction::West      => Direction::East,
+        Direction::NorthWest => Direction::SouthEast,
+    }
+}
+
+fn extract_lines(grid: &[Vec<char>], search: &Search) -> Vec<Line> {
+    let rows = grid.len();
+    let cols = grid.first().map_or(0, |line| line.len());
+    if rows == 0 || cols == 0 {
+        return Vec::new();
+    }
+
+    let wanted = |d: Direction| search.directions.contains(&d) || search.directions.contains(&opposite(d));
+    let mut lines = Vec::new();
+
+    if wanted(Direction::East) {
+        for r in 0..rows {
+            lines.push(Line::new(Direction::East, (0..cols).map(|c| (r, c)), grid));
+        }
+    }
+    if wanted(Direction::South) {
+        for c in 0..cols {
+            lines.push(Line::new(Direction::South, (0..rows).map(|r| (r, c)), grid));
+        }
+    }
+    // diagonals start on the top row or the left/right column
+    if wanted(Direction::SouthEast) {
+        let starts = (0..cols).map(|c| (0, c)).chain((1..rows).map(|r| (r, 0)));
+        for (r, c) in starts {
+            let len = (rows - r).min(cols - c);
+            lines.push(Line::new(Direction::SouthEast, (0..len).map(|i| (r + i, c + i)), grid));
+        }
+    }
+    if wanted(Direction::SouthWest) {
+        let starts = (0..cols).map(|c| (0, c)).chain((1..rows).map(|r| (r, cols - 1)));
+        for (r, c) in starts {
+            let len = (rows - r).min(c + 1);
+            lines.push(Line::new(Direction::SouthWest, (0..len).map(|i| (r + i, c - i)), grid));
+        }
+    }
+
+    lines
+}
+
+pub fn find_words_automaton(grid: &[Vec<char>], words: &[&str], search: &Search) -> Result<Vec<Match>> {
+    if search.wrap {
+        bail!("Wraparound is not supported by the automaton search");
+    }
+    if grid.iter().any(|line| line.len() != grid[0].len()) {
+        bail!("The automaton search needs a rectangular grid");
+    }
+
+    // one pattern per distinct string, each remembering which words it stands for
+    // and whether it is the word read backwards
+    let mut patterns: Vec<String> = Vec::new();
+    let mut owners: HashMap<String, Vec<(usize, bool)>> = HashMap::new();
+    for (i, word) in words.iter().enumerate().filter(|(_, word)| !word.is_empty()) {
+        let reversed: String = word.chars().rev().collect();
+        for (pattern, backwards) in [(word.to_string(), false), (reversed, true)] {
+            let entry = owners.entry(pattern.clone()).or_default();
+            if entry.is_empty() {
+                patterns.push(pattern);
+            }
+            entry.push((i, backwards));
+        }
+    }
+    if patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let automaton = AhoCorasick::new(&patterns)?;
+    let mut matches = Vec::new();
+
+    for line in extract_lines(grid, search) {
+        for hit in automaton.find_overlapping_iter(&line.text) {
+            let pattern = &patterns[hit.pattern().as_usize()];
+            for &(word, backwards) in &owners[pattern] {
+                let ((row, col), direction) = match backwards {
+                    false => (line.coords[hit.start()].expect("hits start on a character"), line.direction),
+                    true => (line.last_coord(hit.end()), opposite(line.direction)),
+                };
+                if search.directions.contains(&direction) {
+                    matches.push(Match { word: words[word].to_string(), row, col, direction });
+                }
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grid as grid;
+    use crate::search::find_words;
+
+    fn sorted(mut matches: Vec<Match>) -> Vec<Match> {
+        matches.sort();
+        matches
+    }
+
+    #[test]
+    fn test_extract_lines() {
+        let data = grid("ABC\nDEF");
+        let lines = extract_lines(&data, &Search::eight_way());
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, vec!["ABC", "DEF", "AD", "BE", "CF", "AE", "BF", "C", "D",
+                               "A", "BD", "CE", "F"]);
+    }
+
+    #[test]
+    fn test_matches_per_cell_search() {
+        let data = grid("XMASAMX\nMMAXSAS\nAXASMMA\nSMXAMXM\nXSAMXAX");
+        let words = ["XMAS", "SAM", "AXA", "M"];
+        for search in [Search::eight_way(), Search::orthogonal()] {
+            let expected = sorted(find_words(&data, &words, &search).unwrap());
+            let actual = sorted(find_words_automaton(&data, &words, &search).unwrap());
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_rejects_unsupported() {
+        let data = grid("XMAS");
+        assert!(find_words_automaton(&data, &["XMAS"], &Search::eight_way().wrapping()).is_err());
+        assert!(find_words_automaton(&grid("XMAS\nXM"), &["XMAS"], &Search::eight_way()).is_err());
+    }
+}
@@ -1,136 +1,183 @@
+mod automaton;
+mod pattern;
+mod render;
+mod search;
+
 use anyhow::Result;
+use automaton::find_words_automaton;
+use pattern::{find_pattern, Template};
+use render::{render, Style};
+use search::{find_words, Search};
+use std::collections::HashSet;
 use std::time::Instant;
 
 // Data cleaning methods
 
 fn parse_data(filepath: &str) -> Result<Vec<Vec<char>>> {
     let data = std::fs::read_to_string(filepath)?;
-    let output = data
-        .lines()
+
+    Ok(parse_grid(&data))
+}
+
+fn parse_grid(text: &str) -> Vec<Vec<char>> {
+    text.lines()
         .map(|line| line
             .chars()
             .collect())
-        .collect();
-
-    Ok(output)
+        .collect()
 }
 
 // Part one methods
 
 fn part_one(filepath: &str) -> Result<i32> {
     let data = parse_data(filepath)?;
-    
-    let mut count = 0;
-    for (row, line) in data.iter().enumerate() {
-        for (col, c) in line.iter().enumerate() {
-            if *c == 'X' {
-                if row > 2 && col > 2 
-                    && data[row - 1][col - 1] == 'M' 
-                    && data[row - 2][col - 2] == 'A' 
-                    && data[row - 3][col - 3] == 'S' {
-                    count += 1;
-                }
-                
-                if row > 2 
-                    && data[row - 1][col] == 'M' 
-                    && data[row - 2][col] == 'A' 
-                    && data[row - 3][col] == 'S' {
-                    count += 1;
-                }
-
-                if row > 2 && col < line.len() - 3
-                    && data[row - 1][col + 1] == 'M' 
-                    && data[row - 2][col + 2] == 'A' 
-                    && data[row - 3][col + 3] == 'S' {
-                    count += 1;
-                }
-
-                if col > 2 
-                    && data[row][col - 1] == 'M' 
-                    && data[row][col - 2] == 'A' 
-                    && data[row][col - 3] == 'S' {
-                    count += 1;
-                }
-
-                if col < line.len() - 3 
-                    && data[row][col + 1] == 'M' 
-                    && data[row][col + 2] == 'A' 
-                    && data[row][col + 3] == 'S' {
-                    count += 1;
-                }
-
-                if row < data.len() - 3 && col > 2
-                    && data[row + 1][col - 1] == 'M' 
-                    && data[row + 2][col - 2] == 'A' 
-                    && data[row + 3][col - 3] == 'S' {
-                    count += 1;
-                }
-
-                if row < data.len() - 3
-                    && data[row + 1][col] == 'M' 
-                    && data[row + 2][col] == 'A' 
-                    && data[row + 3][col] == 'S' {
-                    count += 1;
-                }
-
-                if row < data.len() - 3 && col < line.len() - 3
-                    && data[row + 1][col + 1] == 'M' 
-                    && data[row + 2][col + 2] == 'A' 
-                    && data[row + 3][col + 3] == 'S' {
-                    count += 1;
-                }
-            }
-        }
-    }
+    let matches = find_words(&data, &["XMAS"], &Search::eight_way())?;
 
-    Ok(count)
+    Ok(matches.len() as i32)
 }
 
 fn part_two(filepath: &str) -> Result<i32> {
     let data = parse_data(filepath)?;
-    
-    let mut count = 0;
-    for (row, line) in data.iter().enumerate() {
-        for (col, c) in line.iter().enumerate() {
-            if *c == 'A' && row > 0 && row < data.len() - 1 && col > 0 && col < line.len() - 1 {
-                 if data[row - 1][col - 1] == 'M' &&
-                    data[row - 1][col + 1] == 'M' &&
-                    data[row + 1][col - 1] == 'S' &&
-                    data[row + 1][col + 1] == 'S' {
-                    count += 1;
-                 }
-
-                 if data[row - 1][col - 1] == 'S' &&
-                    data[row - 1][col + 1] == 'S' &&
-                    data[row + 1][col - 1] == 'M' &&
-                    data[row + 1][col + 1] == 'M' {
-                    count += 1;
-                 }
-                 
-
-                 if data[row - 1][col - 1] == 'M' &&
-                    data[row - 1][col + 1] == 'S' &&
-                    data[row + 1][col - 1] == 'M' &&
-                    data[row + 1][col + 1] == 'S' {
-                    count += 1;
-                 }
-
-
-                 if data[row - 1][col - 1] == 'S' &&
-                    data[row - 1][col + 1] == 'M' &&
-                    data[row + 1][col - 1] == 'S' &&
-                    data[row + 1][col + 1] == 'M' {
-                    count += 1;
-                 }
-            }
-        }
+    let x_mas = Template::parse("M.S\n.A.\nM.S", '.')?;
+    let matches = find_pattern(&data, &x_mas);
+
+    Ok(matches.len() as i32)
+}
+
+// word search functions
+
+fn search_words(filepath: &str, words: &[&str], search: &Search, automaton: bool) -> Result<String> {
+    let data = parse_data(filepath)?;
+    let matches = match automaton {
+        true => find_words_automaton(&data, words, search)?,
+        false => find_words(&data, words, search)?,
+    };
+    // a wrapping search has already checked the grid is rectangular and not empty
+    let wrap_at = search.wrap.then(|| (data.len(), data[0].len()));
+
+    let mut output = String::new();
+    for m in &matches {
+        let cells = m.cells(wrap_at);
+        output.push_str(&format!("{} from {:?} to {:?} heading {:?}\n",
+                                 m.word, cells[0], cells[cells.len() - 1], m.direction));
     }
+    output.push_str(&format!("{} matches", matches.len()));
+
+    Ok(output)
+}
+
+// render functions
+
+fn xmas_cells(data: &[Vec<char>]) -> Result<HashSet<(usize, usize)>> {
+    Ok(find_words(data, &["XMAS"], &Search::eight_way())?
+        .iter()
+        .flat_map(|m| m.cells(None))
+        .collect())
+}
+
+fn x_mas_cells(data: &[Vec<char>]) -> Result<HashSet<(usize, usize)>> {
+    let orientations = Template::parse("M.S\n.A.\nM.S", '.')?.orientations();
+
+    Ok(find_pattern(data, &orientations[0])
+        .iter()
+        .flat_map(|m| orientations[m.orientation].cells_at(m.row, m.col))
+        .collect())
+}
+
+fn render_matches(filepath: &str, part_two: bool, style: Style) -> Result<String> {
+    let data = parse_data(filepath)?;
+    let cells = match part_two {
+        true => x_mas_cells(&data)?,
+        false => xmas_cells(&data)?,
+    };
+
+    Ok(render(&data, &cells, style))
+}
+
+// benchmark functions
+
+// A small linear congruential generator so the synthetic grid is the same on
+// every run without pulling in a random number crate.
+fn synthetic_grid(size: usize, alphabet: &[char], seed: u64) -> Vec<Vec<char>> {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        alphabet[(state >> 33) as usize % alphabet.len()]
+    };
 
-    Ok(count)
+    (0..size).map(|_| (0..size).map(|_| next()).collect()).collect()
+}
+
+fn benchmark(size: usize) -> Result<String> {
+    let alphabet: Vec<char> = "XMASETOR".chars().collect();
+    let data = synthetic_grid(size, &alphabet, 2024);
+    let words = ["XMAS", "SAMX", "STAR", "TREE", "MOST", "ROAST", "MASTER", "SEAM", "TAME", "ROSE",
+                 "STORM", "MEAT", "TEAM", "MATE", "EAST", "SEAT", "REST", "TOE", "ORE", "ARM"];
+    let search = Search::eight_way();
+    let mut output = format!("{}x{} grid, {} words\n", size, size, words.len());
+
+    let now = Instant::now();
+    let per_cell = find_words(&data, &words, &search)?.len();
+    output.push_str(&format!("Per cell search:  {} matches in {:.2?}\n", per_cell, now.elapsed()));
+
+    let now = Instant::now();
+    let automaton = find_words_automaton(&data, &words, &search)?.len();
+    output.push_str(&format!("Automaton search: {} matches in {:.2?}", automaton, now.elapsed()));
+
+    if per_cell != automaton {
+        anyhow::bail!("The two searches disagree: {} vs {} matches", per_cell, automaton);
+    }
+
+    Ok(output)
 }
 
 fn main() {
     let filepath = "input.txt";
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("render") {
+        let part_two = args.iter().any(|arg| arg == "--part-two");
+        let style = if args.iter().any(|arg| arg == "--html") {
+            Style::Html
+        } else if args.iter().any(|arg| arg == "--ansi") {
+            Style::Ansi
+        } else {
+            Style::Dots
+        };
+
+        match render_matches(filepath, part_two, style) {
+            Ok(output) => print!("{}", output),
+            Err(e) => println!("Error: Could not render the matches. {}", e),
+        };
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let size = args.get(2).and_then(|s| s.parse::<usize>().ok()).unwrap_or(1000);
+        match benchmark(size) {
+            Ok(output) => println!("{}", output),
+            Err(e) => println!("Error: Could not run the benchmark. {}", e),
+        };
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("search") {
+        let automaton = args.iter().any(|arg| arg == "--automaton");
+        let words: Vec<&str> = args[2..].iter().filter(|a| !a.starts_with("--")).map(String::as_str).collect();
+        let mut search = match args.iter().any(|arg| arg == "--orthogonal") {
+            true => Search::orthogonal(),
+            false => Search::eight_way(),
+        };
+        if args.iter().any(|arg| arg == "--wrap") {
+            search = search.wrapping();
+        }
+
+        match search_words(filepath, &words, &search, automaton) {
+            Ok(output) => println!("{}", output),
+            Err(e) => println!("Error: Could not search for words. {}", e),
+        };
+        return;
+    }
 
     println!("Hello, Advent of Code 2024!");
 
@@ -156,6 +203,17 @@ fn main() {
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX";
+
     #[test]
     fn test_main() {
         main();
@@ -172,4 +230,50 @@ mod tests {
         let filepath = "part_two_test_input.txt";
         assert_eq!(part_two(filepath).unwrap(), 9);
     }
+
+    #[test]
+    fn test_xmas_example() {
+        let data = parse_grid(EXAMPLE);
+        assert_eq!(find_words(&data, &["XMAS"], &Search::eight_way()).unwrap().len(), 18);
+    }
+
+    #[test]
+    fn test_x_mas_example() {
+        let data = parse_grid(EXAMPLE);
+        let x_mas = Template::parse("M.S\n.A.\nM.S", '.').unwrap();
+        assert_eq!(find_pattern(&data, &x_mas).len(), 9);
+    }
+
+    #[test]
+    fn test_render_example() {
+        let data = parse_grid(EXAMPLE);
+        assert_eq!(render(&data, &xmas_cells(&data).unwrap(), Style::Dots), "....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+");
+        assert_eq!(render(&data, &x_mas_cells(&data).unwrap(), Style::Dots), ".M.S......
+..A..MSMS.
+.M.S.MAA..
+..A.ASMSM.
+.M.S.M....
+..........
+S.S.S.S.S.
+.A.A.A.A..
+M.M.M.M.M.
+..........
+");
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let output = benchmark(50).unwrap();
+        assert!(output.starts_with("50x50 grid, 20 words"));
+    }
 }
@@ -0,0 +1,185 @@
+use anyhow::{bail, Result};
+
+// Matches a small 2D template against a grid of characters. The template is
+// written once and every distinct rotation and reflection of it is tried, so
+// symmetric shapes are not counted more than once per position.
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Template {
+    // None is a wildcard that matches any character
+    cells: Vec<Vec<Option<char>>>,
+}
+
+impl Template {
+    pub fn parse(text: &str, wildcard: char) -> Result<Self> {
+        let cells: Vec<Vec<Option<char>>> = text
+            .lines()
+            .map(|line| line
+                .chars()
+                .map(|c| if c == wildcard { None } else { Some(c) })
+                .collect())
+            .collect();
+
+        let width = cells.first().map_or(0, |row| row.len());
+        if width == 0 {
+            bail!("Template is empty");
+        }
+        if cells.iter().any(|row| row.len() != width) {
+            bail!("Template rows are not all {} characters wide", width);
+        }
+
+        Ok(Template { cells })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cells[0].len()
+    }
+
+    // 90 degrees clockwise
+    fn rotate(&self) -> Template {
+        let rows = self.rows();
+        let cells = (0..self.cols())
+            .map(|c| (0..rows).rev().map(|r| self.cells[r][c]).collect())
+            .collect();
+
+        Template { cells }
+    }
+
+    // mirrored left to right
+    fn reflect(&self) -> Template {
+        let cells = self.cells
+            .iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+
+        Template { cells }
+    }
+
+    // Every distinct rotation and reflection, starting with the template as written.
+    pub fn orientations(&self) -> Vec<Template> {
+        let mut orientations: Vec<Template> = Vec::new();
+        let mut current = self.clone();
+
+        for _ in 0..4 {
+            for candidate in [current.clone(), current.reflect()] {
+                if !orientations.contains(&candidate) {
+                    orientations.push(candidate);
+                }
+            }
+            current = current.rotate();
+        }
+
+        orientations
+    }
+
+    // Grid coordinates of the non-wildcard cells when placed with its top left at (row, col).
+    pub fn cells_at(&self, row: usize, col: usize) -> Vec<(usize, usize)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .flat_map(|(r, line)| line
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.is_some())
+                .map(move |(c, _)| (row + r, col + c)))
+            .collect()
+    }
+
+    // The whole template has to fit on the grid, wildcards included, so that a
+    // shape hanging off the bottom or right edge doesn't match when the same
+    // shape hanging off the top or left can't.
+    fn matches_at(&self, grid: &[Vec<char>], row: usize, col: usize) -> bool {
+        if row + self.rows() > grid.len() {
+            return false;
+        }
+
+        self.cells.iter().enumerate().all(|(r, line)| {
+            let grid_row = &grid[row + r];
+            col + self.cols() <= grid_row.len()
+                && line.iter().enumerate().all(|(c, cell)| cell.is_none_or(|expected| grid_row[col + c] == expected))
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct PatternMatch {
+    // top left corner of the oriented template
+    pub row: usize,
+    pub col: usize,
+    // index into Template::orientations
+    pub orientation: usize,
+}
+
+pub fn find_pattern(grid: &[Vec<char>], template: &Template) -> Vec<PatternMatch> {
+    let orientations = template.orientations();
+    let mut matches = Vec::new();
+
+    for row in 0..grid.len() {
+        for col in 0..grid[row].len() {
+            for (orientation, oriented) in orientations.iter().enumerate() {
+                if oriented.matches_at(grid, row, col) {
+                    matches.push(PatternMatch { row, col, orientation });
+                }
+            }
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grid as grid;
+
+    #[test]
+    fn test_parse() {
+        let template = Template::parse("M.S\n.A.", '.').unwrap();
+        assert_eq!(template.rows(), 2);
+        assert_eq!(template.cols(), 3);
+        assert_eq!(template.cells[1], vec![None, Some('A'), None]);
+
+        assert!(Template::parse("", '.').is_err());
+        assert!(Template::parse("M.S\n.A", '.').is_err());
+    }
+
+    #[test]
+    fn test_orientations() {
+        let x_mas = Template::parse("M.S\n.A.\nM.S", '.').unwrap();
+        let orientations = x_mas.orientations();
+        assert_eq!(orientations.len(), 4);
+        assert_eq!(orientations[0], x_mas);
+        assert!(orientations.contains(&Template::parse("M.M\n.A.\nS.S", '.').unwrap()));
+
+        let symmetric = Template::parse("#.#\n.#.\n#.#", '.').unwrap();
+        assert_eq!(symmetric.orientations().len(), 1);
+
+        let l_shape = Template::parse("#.\n#.\n##", '.').unwrap();
+        assert_eq!(l_shape.orientations().len(), 8);
+    }
+
+    #[test]
+    fn test_find_pattern() {
+        let data = grid("S.M.\n.A..\nS.MX\n....");
+        let x_mas = Template::parse("M.S\n.A.\nM.S", '.').unwrap();
+        let matches = find_pattern(&data, &x_mas);
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].row, matches[0].col), (0, 0));
+        assert_eq!(x_mas.orientations()[matches[0].orientation], Template::parse("S.M\n.A.\nS.M", '.').unwrap());
+        assert_eq!(x_mas.cells_at(1, 2), vec![(1, 2), (1, 4), (2, 3), (3, 2), (3, 4)]);
+
+        let l_shape = Template::parse("AB\nC.", '.').unwrap();
+        let data = grid("CA\n.B");
+        assert_eq!(find_pattern(&data, &l_shape).len(), 1);
+
+        // wildcards don't match past any edge of the grid
+        let corner = Template::parse("#.\n..", '.').unwrap();
+        assert!(find_pattern(&grid("#"), &corner).is_empty());
+        assert!(find_pattern(&grid("#\n.."), &corner).is_empty());
+        assert_eq!(find_pattern(&grid("#.\n.."), &corner).len(), 1);
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+
+// Renders the grid with the letters that took part in a match picked out, either
+// the way the puzzle text does it (everything else replaced by '.'), with ANSI
+// colors for a terminal, or as a standalone HTML page.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Style {
+    Dots,
+    Ansi,
+    Html,
+}
+
+const ANSI_MATCH: &str = "\x1b[1;32m";
+const ANSI_OTHER: &str = "\x1b[2m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+fn escape_html(c: char) -> String {
+    match c {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        _ => c.to_string(),
+    }
+}
+
+pub fn render(grid: &[Vec<char>], cells: &HashSet<(usize, usize)>, style: Style) -> String {
+    let mut output = String::new();
+    if style == Style::Html {
+        output.push_str("<!DOCTYPE html>\n<html>\n<head>\n<style>\n");
+        output.push_str("pre { font-family: monospace; color: #888; }\n");
+        output.push_str(".match { color: #0a0; font-weight: bold; }\n");
+        output.push_str("</style>\n</head>\n<body>\n<pre>\n");
+    }
+
+    for (row, line) in grid.iter().enumerate() {
+        for (col, &c) in line.iter().enumerate() {
+            let hit = cells.contains(&(row, col));
+            match (style, hit) {
+                (Style::Dots, true) => output.push(c),
+                (Style::Dots, false) => output.push('.'),
+                (Style::Ansi, true) => output.push_str(&format!("{}{}{}", ANSI_MATCH, c, ANSI_RESET)),
+                (Style::Ansi, false) => output.push_str(&format!("{}{}{}", ANSI_OTHER, c, ANSI_RESET)),
+                (Style::Html, true) => output.push_str(&format!("<span class=\"match\">{}</span>", escape_html(c))),
+                (Style::Html, false) => output.push_str(&escape_html(c)),
+            }
+        }
+        output.push('\n');
+    }
+
+    if style == Style::Html {
+        output.push_str("</pre>\n</body>\n</html>\n");
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let grid = vec![vec!['X', '<'], vec!['A', 'S']];
+        let cells: HashSet<(usize, usize)> = [(0, 1), (1, 1)].into_iter().collect();
+
+        assert_eq!(render(&grid, &cells, Style::Dots), ".<\n.S\n");
+        assert_eq!(render(&grid, &cells, Style::Ansi),
+                   "\x1b[2mX\x1b[0m\x1b[1;32m<\x1b[0m\n\x1b[2mA\x1b[0m\x1b[1;32mS\x1b[0m\n");
+
+        let html = render(&grid, &cells, Style::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<pre>\nX<span class=\"match\">&lt;</span>\nA<span class=\"match\">S</span>\n</pre>"));
+    }
+}
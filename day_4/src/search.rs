@@ -0,0 +1,203 @@
+// A general word search over a grid of characters. Every (cell, direction)
+// pair is tried as a starting point for every word, and the search can be
+// limited to a subset of directions or allowed to wrap around the edges.
+
+use anyhow::{bail, Result};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    pub const ORTHOGONAL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    // (row, col) step taken for each letter
+    pub fn delta(&self) -> (isize, isize) {
+        match self {
+            Direction::North     => (-1, 0),
+            Direction::NorthEast => (-1, 1),
+            Direction::East      => (0, 1),
+            Direction::SouthEast => (1, 1),
+            Direction::South     => (1, 0),
+            Direction::SouthWest => (1, -1),
+            Direction::West      => (0, -1),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub directions: Vec<Direction>,
+    pub wrap: bool,
+}
+
+impl Search {
+    pub fn eight_way() -> Self {
+        Search { directions: Direction::ALL.to_vec(), wrap: false }
+    }
+
+    pub fn orthogonal() -> Self {
+        Search { directions: Direction::ORTHOGONAL.to_vec(), wrap: false }
+    }
+
+    pub fn wrapping(self) -> Self {
+        Search { wrap: true, ..self }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+pub struct Match {
+    pub word: String,
+    pub row: usize,
+    pub col: usize,
+    pub direction: Direction,
+}
+
+impl Match {
+    // Grid coordinates of every letter in the match, in word order. Matches
+    // from a wrapping search pass the grid's (rows, cols) to wrap round at.
+    pub fn cells(&self, wrap_at: Option<(usize, usize)>) -> Vec<(usize, usize)> {
+        let (dr, dc) = self.direction.delta();
+        (0..self.word.chars().count() as isize)
+            .map(|i| {
+                let mut r = self.row as isize + dr * i;
+                let mut c = self.col as isize + dc * i;
+                if let Some((rows, cols)) = wrap_at {
+                    r = r.rem_euclid(rows as isize);
+                    c = c.rem_euclid(cols as isize);
+                }
+                (r as usize, c as usize)
+            })
+            .collect()
+    }
+}
+
+fn step(grid: &[Vec<char>], row: usize, col: usize, delta: (isize, isize), i: usize, wrap: bool) -> Option<char> {
+    let rows = grid.len() as isize;
+    let cols = grid.first().map_or(0, |line| line.len()) as isize;
+    let mut r = row as isize + delta.0 * i as isize;
+    let mut c = col as isize + delta.1 * i as isize;
+
+    if wrap {
+        r = r.rem_euclid(rows);
+        c = c.rem_euclid(cols);
+    }
+    if r < 0 || c < 0 {
+        return None;
+    }
+
+    grid.get(r as usize)?.get(c as usize).copied()
+}
+
+fn matches_at(grid: &[Vec<char>], word: &[char], row: usize, col: usize, direction: Direction, wrap: bool) -> bool {
+    word.iter()
+        .enumerate()
+        .all(|(i, &letter)| step(grid, row, col, direction.delta(), i, wrap) == Some(letter))
+}
+
+pub fn find_words(grid: &[Vec<char>], words: &[&str], search: &Search) -> Result<Vec<Match>> {
+    // wrapping takes every row's width from the first one
+    if search.wrap && grid.first().is_none_or(|line| line.is_empty()) {
+        bail!("Wraparound needs a grid with at least one column");
+    }
+    if search.wrap && grid.iter().any(|line| line.len() != grid[0].len()) {
+        bail!("Wraparound needs a rectangular grid");
+    }
+
+    let words: Vec<(&str, Vec<char>)> = words
+        .iter()
+        .filter(|word| !word.is_empty())
+        .map(|&word| (word, word.chars().collect()))
+        .collect();
+    let mut matches = Vec::new();
+
+    for (row, line) in grid.iter().enumerate() {
+        for (col, &c) in line.iter().enumerate() {
+            for (word, letters) in &words {
+                if letters[0] != c {
+                    continue;
+                }
+                for &direction in &search.directions {
+                    if matches_at(grid, letters, row, col, direction, search.wrap) {
+                        matches.push(Match { word: word.to_string(), row, col, direction });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grid as grid;
+
+    #[test]
+    fn test_find_words_directions() {
+        let data = grid("XMAS\nMM..\nA.A.\nS..S");
+        let matches = find_words(&data, &["XMAS"], &Search::eight_way()).unwrap();
+        let mut directions: Vec<Direction> = matches.iter().map(|m| m.direction).collect();
+        directions.sort();
+        assert_eq!(directions, vec![Direction::East, Direction::SouthEast, Direction::South]);
+
+        let matches = find_words(&data, &["XMAS"], &Search::orthogonal()).unwrap();
+        assert_eq!(matches.len(), 2);
+    }
+
+    #[test]
+    fn test_find_words_wrapping() {
+        let data = grid("ASXM");
+        assert!(find_words(&data, &["XMAS"], &Search::orthogonal()).unwrap().is_empty());
+
+        let matches = find_words(&data, &["XMAS"], &Search::orthogonal().wrapping()).unwrap();
+        assert_eq!(matches, vec![Match { word: "XMAS".to_string(), row: 0, col: 2, direction: Direction::East }]);
+        assert_eq!(matches[0].cells(Some((1, 4))), vec![(0, 2), (0, 3), (0, 0), (0, 1)]);
+
+        assert!(find_words(&grid("\nXMAS"), &["XMAS"], &Search::orthogonal().wrapping()).is_err());
+        assert!(find_words(&grid("ASXM\nXMASXM"), &["XMAS"], &Search::orthogonal().wrapping()).is_err());
+        assert_eq!(find_words(&grid("\nXMAS"), &["XMAS"], &Search::orthogonal()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_find_words_multiple() {
+        let data = grid("CAT.\nDOG.\n....");
+        let matches = find_words(&data, &["CAT", "DOG", "TAC", "COD"], &Search::eight_way()).unwrap();
+        let words: Vec<&str> = matches.iter().map(|m| m.word.as_str()).collect();
+        assert_eq!(words, vec!["CAT", "TAC", "DOG"]);
+    }
+
+    #[test]
+    fn test_cells_ragged() {
+        let data = grid("XM\nXMAS");
+        let matches = find_words(&data, &["XMAS"], &Search::orthogonal()).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].cells(None), vec![(1, 0), (1, 1), (1, 2), (1, 3)]);
+    }
+}
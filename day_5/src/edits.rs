@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// Explains how to get from an update to its corrected order. Once the rules fix
+// a single target order, the fewest adjacent swaps is the number of inversions
+// (bubble sort performs exactly those), and the fewest moves of a single page is
+// the update length minus its longest run of pages already in target order.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Edit {
+    // swap the pages at `index` and `index + 1`
+    Swap { index: usize, pages: (i32, i32) },
+    // take `page` out at `from` and put it back so it ends up at `to`
+    Move { page: i32, from: usize, to: usize },
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Edit::Swap { index, pages: (a, b) } => {
+                write!(f, "swap {} and {} at positions {} and {}", a, b, index, index + 1)
+            },
+            Edit::Move { page, from, to } => write!(f, "move {} from position {} to {}", page, from, to),
+        }
+    }
+}
+
+fn ranks(target: &[i32]) -> HashMap<i32, usize> {
+    target.iter().enumerate().map(|(i, &page)| (page, i)).collect()
+}
+
+pub fn adjacent_swaps(list: &[i32], target: &[i32]) -> Vec<Edit> {
+    let rank = ranks(target);
+    let mut current = list.to_vec();
+    let mut edits = Vec::new();
+
+    for end in (1..current.len()).rev() {
+        for index in 0..end {
+            if rank[&current[index]] > rank[&current[index + 1]] {
+                edits.push(Edit::Swap { index, pages: (current[index], current[index + 1]) });
+                current.swap(index, index + 1);
+            }
+        }
+    }
+
+    edits
+}
+
+// Indices of one longest subsequence of `values` that is strictly increasing.
+fn longest_increasing(values: &[usize]) -> Vec<usize> {
+    // tails[k] is the index ending the best increasing run of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; values.len()];
+
+    for (i, &value) in values.iter().enumerate() {
+        let k = tails.partition_point(|&t| values[t] < value);
+        previous[i] = k.checked_sub(1).map(|k| tails[k]);
+        match k == tails.len() {
+            true => tails.push(i),
+            false => tails[k] = i,
+        }
+    }
+
+    let mut indices = Vec::new();
+    let mut cursor = tails.last().copied();
+    while let Some(i) = cursor {
+        indices.push(i);
+        cursor = previous[i];
+    }
+    indices.reverse();
+
+    indices
+}
+
+pub fn page_moves(list: &[i32], target: &[i32]) -> Vec<Edit> {
+    let rank = ranks(target);
+    let ranked: Vec<usize> = list.iter().map(|page| rank[page]).collect();
+    let mut placed: Vec<bool> = vec![false; target.len()];
+    for i in longest_increasing(&ranked) {
+        placed[ranked[i]] = true;
+    }
+
+    let mut current = list.to_vec();
+    let mut edits = Vec::new();
+
+    // Every page outside the kept run is moved in target order to sit right
+    // after the closest page before it that is already in place.
+    for (r, &page) in target.iter().enumerate() {
+        if placed[r] {
+            continue;
+        }
+
+        let from = current.iter().position(|&p| p == page).expect("page is in the update");
+        current.remove(from);
+        let to = match (0..r).rev().find(|&before| placed[before]) {
+            Some(before) => current.iter().position(|&p| p == target[before]).expect("page is in the update") + 1,
+            None => 0,
+        };
+        current.insert(to, page);
+        placed[r] = true;
+
+        edits.push(Edit::Move { page, from, to });
+    }
+
+    edits
+}
+
+pub fn apply(list: &[i32], edits: &[Edit]) -> Vec<i32> {
+    let mut current = list.to_vec();
+    for edit in edits {
+        match *edit {
+            Edit::Swap { index, .. } => current.swap(index, index + 1),
+            Edit::Move { from, to, .. } => {
+                let page = current.remove(from);
+                current.insert(to, page);
+            },
+        }
+    }
+
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_increasing() {
+        assert_eq!(longest_increasing(&[0, 4, 1, 3, 2]), vec![0, 2, 4]);
+        assert_eq!(longest_increasing(&[3, 2, 1]), vec![2]);
+        assert_eq!(longest_increasing(&[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_adjacent_swaps() {
+        let list = [97, 13, 75, 29, 47];
+        let target = [97, 75, 47, 29, 13];
+        let edits = adjacent_swaps(&list, &target);
+        assert_eq!(edits.len(), 4);
+        assert_eq!(edits[0], Edit::Swap { index: 1, pages: (13, 75) });
+        assert_eq!(apply(&list, &edits), target);
+
+        assert!(adjacent_swaps(&target, &target).is_empty());
+    }
+
+    #[test]
+    fn test_page_moves() {
+        let list = [97, 13, 75, 29, 47];
+        let target = [97, 75, 47, 29, 13];
+        let edits = page_moves(&list, &target);
+        assert_eq!(edits, vec![Edit::Move { page: 29, from: 3, to: 4 },
+                               Edit::Move { page: 13, from: 1, to: 4 }]);
+        assert_eq!(apply(&list, &edits), target);
+
+        let list = [5, 1, 2, 3, 4];
+        let target = [1, 2, 3, 4, 5];
+        let edits = page_moves(&list, &target);
+        assert_eq!(edits, vec![Edit::Move { page: 5, from: 0, to: 4 }]);
+        assert_eq!(edits[0].to_string(), "move 5 from position 0 to 4");
+        assert_eq!(apply(&list, &edits), target);
+    }
+}
@@ -0,0 +1,198 @@
+use multimap::MultiMap;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+// The page ordering rules as a directed graph, X -> Y for every rule X|Y, with
+// the analysis used to sanity check a rule set and Graphviz export.
+
+#[derive(Debug, Clone, Default)]
+pub struct RuleGraph {
+    edges: BTreeMap<i32, BTreeSet<i32>>,
+}
+
+impl RuleGraph {
+    pub fn new(page_map: &MultiMap<i32, i32>) -> Self {
+        let mut edges: BTreeMap<i32, BTreeSet<i32>> = BTreeMap::new();
+        for (&before, afters) in page_map.iter_all() {
+            for &after in afters {
+                edges.entry(before).or_default().insert(after);
+                edges.entry(after).or_default();
+            }
+        }
+
+        RuleGraph { edges }
+    }
+
+    // Only the pages of one update and the rules between them.
+    pub fn subgraph(&self, pages: &[i32]) -> Self {
+        let keep: BTreeSet<i32> = pages.iter().copied().collect();
+        let edges = keep
+            .iter()
+            .map(|page| {
+                let afters = self.successors(*page).filter(|after| keep.contains(after)).collect();
+                (*page, afters)
+            })
+            .collect();
+
+        RuleGraph { edges }
+    }
+
+    pub fn pages(&self) -> impl Iterator<Item = i32> + '_ {
+        self.edges.keys().copied()
+    }
+
+    pub fn successors(&self, page: i32) -> impl Iterator<Item = i32> + '_ {
+        self.edges.get(&page).into_iter().flatten().copied()
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.edges.values().map(|afters| afters.len()).sum()
+    }
+
+    // Tarjan's algorithm. Components come out in reverse topological order.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<i32>> {
+        struct State {
+            index: BTreeMap<i32, usize>,
+            lowlink: BTreeMap<i32, usize>,
+            stack: Vec<i32>,
+            on_stack: BTreeSet<i32>,
+            components: Vec<Vec<i32>>,
+        }
+
+        fn connect(graph: &RuleGraph, page: i32, state: &mut State) {
+            let index = state.index.len();
+            state.index.insert(page, index);
+            state.lowlink.insert(page, index);
+            state.stack.push(page);
+            state.on_stack.insert(page);
+
+            for next in graph.successors(page) {
+                if !state.index.contains_key(&next) {
+                    connect(graph, next, state);
+                    let low = state.lowlink[&page].min(state.lowlink[&next]);
+                    state.lowlink.insert(page, low);
+                } else if state.on_stack.contains(&next) {
+                    let low = state.lowlink[&page].min(state.index[&next]);
+                    state.lowlink.insert(page, low);
+                }
+            }
+
+            if state.lowlink[&page] == state.index[&page] {
+                let mut component = Vec::new();
+                while let Some(member) = state.stack.pop() {
+                    state.on_stack.remove(&member);
+                    component.push(member);
+                    if member == page {
+                        break;
+                    }
+                }
+                component.sort();
+                state.components.push(component);
+            }
+        }
+
+        let mut state = State {
+            index: BTreeMap::new(),
+            lowlink: BTreeMap::new(),
+            stack: Vec::new(),
+            on_stack: BTreeSet::new(),
+            components: Vec::new(),
+        };
+        for page in self.pages() {
+            if !state.index.contains_key(&page) {
+                connect(self, page, &mut state);
+            }
+        }
+
+        state.components
+    }
+
+    // Components that contain a cycle: more than one page, or a page with a rule
+    // pointing at itself.
+    pub fn cycles(&self) -> Vec<Vec<i32>> {
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|component| component.len() > 1 || self.successors(component[0]).any(|next| next == component[0]))
+            .collect()
+    }
+
+    fn reachable_without(&self, from: i32, to: i32, skip: (i32, i32)) -> bool {
+        let mut seen = BTreeSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(page) = queue.pop_front() {
+            for next in self.successors(page) {
+                if (page, next) == skip {
+                    continue;
+                }
+                if next == to {
+                    return true;
+                }
+                if seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        false
+    }
+
+    // Rules X|Y that are already implied by a longer chain X|A, A|B, ..., B|Y.
+    pub fn transitive_redundancies(&self) -> Vec<(i32, i32)> {
+        self.edges
+            .iter()
+            .flat_map(|(&before, afters)| afters.iter().map(move |&after| (before, after)))
+            .filter(|&(before, after)| before != after && self.reachable_without(before, after, (before, after)))
+            .collect()
+    }
+
+    pub fn to_dot(&self, name: &str) -> String {
+        let mut output = format!("digraph \"{}\" {{\n", name);
+        for (page, afters) in &self.edges {
+            if afters.is_empty() {
+                output.push_str(&format!("    {};\n", page));
+            }
+            for after in afters {
+                output.push_str(&format!("    {} -> {};\n", page, after));
+            }
+        }
+        output.push_str("}\n");
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(rules: &[(i32, i32)]) -> RuleGraph {
+        RuleGraph::new(&rules.iter().copied().collect())
+    }
+
+    #[test]
+    fn test_components_and_cycles() {
+        let rules = graph(&[(1, 2), (2, 3), (3, 1), (3, 4), (4, 5), (6, 6)]);
+        let mut components = rules.strongly_connected_components();
+        components.sort();
+        assert_eq!(components, vec![vec![1, 2, 3], vec![4], vec![5], vec![6]]);
+
+        let mut cycles = rules.cycles();
+        cycles.sort();
+        assert_eq!(cycles, vec![vec![1, 2, 3], vec![6]]);
+    }
+
+    #[test]
+    fn test_transitive_redundancies() {
+        let rules = graph(&[(1, 2), (2, 3), (1, 3), (3, 4), (1, 4), (2, 5)]);
+        assert_eq!(rules.transitive_redundancies(), vec![(1, 3), (1, 4)]);
+    }
+
+    #[test]
+    fn test_subgraph_and_dot() {
+        let rules = graph(&[(47, 53), (97, 13), (97, 47), (75, 47)]);
+        assert_eq!(rules.rule_count(), 4);
+
+        let update = rules.subgraph(&[97, 47, 53, 29]);
+        assert_eq!(update.rule_count(), 2);
+        assert_eq!(update.to_dot("update"), "digraph \"update\" {\n    29;\n    47 -> 53;\n    53;\n    97 -> 47;\n}\n");
+    }
+}
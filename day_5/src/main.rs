@@ -1,5 +1,13 @@
-use anyhow::{Context, Result};
-use std::time::Instant;
+mod edits;
+mod graph;
+mod rules;
+
+use anyhow::{bail, Context, Result};
+use graph::RuleGraph;
+use rules::Rules;
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+use std::collections::{BTreeSet, HashMap};
 use multimap::MultiMap;
 
 // convenience structs
@@ -7,6 +15,7 @@ use multimap::MultiMap;
 #[derive(Debug)]
 struct Data {
     page_map: MultiMap<i32,i32>,
+    rules: Rules,
     page_lists: Vec<Vec<i32>>,
 }
 
@@ -14,65 +23,62 @@ struct Data {
 
 fn get_input(filepath: &str) -> Result<Data> {
     let raw_data = std::fs::read_to_string(filepath)?;
-    let (page_data, list_data)  = raw_data.split_once("\n\n").with_context(|| "Could not split data")?;
-    
-    let page_map: MultiMap<i32, i32> = page_data
-        .lines()
-        .map(|line| {
-            let (key, value) = line
-                .split_once("|")
-                .with_context(|| "Could not split line")?;
-            let key = key
-                .trim()
-                .parse::<i32>()
-                .with_context(|| "Could not parse the key to an integer")?;
-            let value = value
-                .trim()
-                .parse::<i32>()
-                .with_context(|| format!("Could not parse '{}' to an integer", value))?;
-            Ok((key, value))
-        })
-        .collect::<Result<MultiMap<i32,i32>>>()?;
-
-    let page_lists: Vec<Vec<i32>> = list_data
-        .lines()
-        .map(|line| {
-            line.split(",")
-                .map(|x| x.parse::<i32>()
-                    .with_context(|| format!("Could not parse '{}' to an integer", x)))
-                .collect::<Result<Vec<i32>>>()
-        })
-        .collect::<Result<Vec<Vec<i32>>>>()?;
-
-    Ok(Data { page_map, page_lists })
+    parse_input(&raw_data)
 }
 
-// Part 1 Functions
+fn parse_page(token: &str, number: usize) -> Result<i32> {
+    let token = token.trim();
+    token
+        .parse::<i32>()
+        .with_context(|| format!("Line {}: Could not parse '{}' to an integer", number, token))
+}
 
-fn check_order(page_map: &MultiMap<i32, i32>, list: &Vec<i32>) -> bool {
-    let mut last = match list.last() {
-        Some(x) => *x,
-        None => return false,
-    };
+// Rules come first and updates second. Blank lines anywhere are skipped, so the
+// sections no longer have to be separated by exactly one empty line, and
+// anything after a '#' is a comment. Lines are trimmed, which also takes care
+// of the '\r' left behind by CRLF line endings.
+fn parse_input(raw_data: &str) -> Result<Data> {
+    let mut page_map: MultiMap<i32, i32> = MultiMap::new();
+    let mut page_lists: Vec<Vec<i32>> = Vec::new();
+
+    for (i, line) in raw_data.lines().enumerate() {
+        let number = i + 1;
+        let line = line.split_once('#').map_or(line, |(content, _)| content).trim();
+        if line.is_empty() {
+            continue;
+        }
 
-    for x in list.into_iter().rev() {
-        if *x == last {
+        if let Some((key, value)) = line.split_once('|') {
+            if !page_lists.is_empty() {
+                bail!("Line {}: Found the rule '{}' after the updates had started", number, line);
+            }
+            page_map.insert(parse_page(key, number)?, parse_page(value, number)?);
             continue;
         }
-        let page_list = match page_map.get_vec(x) {
-            Some(x) => x,
-            None => return false,
-        };
-        if page_list.contains(&last) {
-            last = *x;
-        } else {
-            return false;
+
+        let pages = line
+            .split(',')
+            .map(|page| parse_page(page, number))
+            .collect::<Result<Vec<i32>>>()?;
+        if pages.len() % 2 == 0 {
+            bail!("Line {}: The update '{}' has {} pages, but needs an odd number to have a middle page",
+                  number, line, pages.len());
         }
+        page_lists.push(pages);
     }
-    true
+
+    let rules = Rules::new(&page_map);
+
+    Ok(Data { page_map, rules, page_lists })
 }
 
-fn find_middle(list: &Vec<i32>) -> i32 {
+// Part 1 Functions
+
+fn check_order(rules: &Rules, list: &[i32]) -> bool {
+    !list.is_empty() && list.windows(2).all(|pair| pair[0] == pair[1] || rules.before(pair[0], pair[1]))
+}
+
+fn find_middle(list: &[i32]) -> i32 {
     list[(list.len()-1) / 2]
 }
 
@@ -81,7 +87,7 @@ fn part_one(filepath: &str) -> Result<i32> {
 
     let mut sum: i32 = 0;
     for line in data.page_lists {
-        if check_order(&data.page_map, &line) {
+        if check_order(&data.rules, &line) {
             sum += find_middle(&line);
         } 
     }
@@ -91,48 +97,329 @@ fn part_one(filepath: &str) -> Result<i32> {
 
 // Part 2 Functions
 
-fn order_line(page_map: &MultiMap<i32, i32>, list: &Vec<i32>) -> Result<Vec<i32>> {
-    let mut ordered_list = vec![0; list.len()];
+// Pages in the update that the rules say must come after `page`.
+fn successors<'a>(rules: &'a Rules, page: &i32, list: &'a [i32]) -> impl Iterator<Item = &'a i32> {
+    let page = *page;
+    list.iter().filter(move |&&next| rules.before(page, next))
+}
 
-    for first in list {
-        let mut counter = 0;
-        let page_list = match page_map.get_vec(first) {
-            Some(list) => list,
-            None => {
-                ordered_list[list.len()-1] = *first;
-                continue;
-            }
+// Walks backwards from `start` through pages that still have an unplaced
+// predecessor until one repeats, which closes the cycle.
+fn find_cycle(rules: &Rules, remaining: &[i32], start: i32) -> Vec<i32> {
+    let mut path = vec![start];
+    loop {
+        let current = path[path.len() - 1];
+        let previous = remaining
+            .iter()
+            .find(|page| successors(rules, page, remaining).any(|next| *next == current))
+            .copied()
+            .expect("every remaining page has a remaining predecessor");
+
+        if let Some(i) = path.iter().position(|&page| page == previous) {
+            let mut cycle: Vec<i32> = path[i..].iter().rev().copied().collect();
+            cycle.push(cycle[0]);
+            return cycle;
+        }
+        path.push(previous);
+    }
+}
+
+// Kahn's algorithm over the rules that mention two pages of the update. The
+// order is only well defined if exactly one page is ready at every step, so
+// two ready pages or none at all are reported as errors.
+fn order_line(rules: &Rules, list: &[i32]) -> Result<Vec<i32>> {
+    let mut indegree: HashMap<i32, usize> = list.iter().map(|&page| (page, 0)).collect();
+    for page in list {
+        for next in successors(rules, page, list) {
+            *indegree.get_mut(next).expect("successors are in the update") += 1;
+        }
+    }
+
+    let mut remaining = list.to_vec();
+    let mut ordered_list = Vec::with_capacity(list.len());
+    while !remaining.is_empty() {
+        let ready: Vec<i32> = remaining.iter().filter(|page| indegree[page] == 0).copied().collect();
+        let page = match ready.as_slice() {
+            [page] => *page,
+            [] => {
+                let cycle = find_cycle(rules, &remaining, remaining[0]);
+                let cycle: Vec<String> = cycle.iter().map(|page| page.to_string()).collect();
+                bail!("The rules for update {:?} contain a cycle: {}", list, cycle.join(" -> "));
+            },
+            [first, second, ..] => {
+                bail!("The rules for update {:?} leave pages {} and {} unordered", list, first, second);
+            },
         };
-        for second in list {
-            if first == second {
-                continue;
-            }
-            if page_list.contains(second) {
-                counter += 1;
-            }
+
+        for next in successors(rules, &page, &remaining) {
+            *indegree.get_mut(next).expect("successors are in the update") -= 1;
         }
-        ordered_list[counter] = *first;
+        remaining.retain(|&other| other != page);
+        ordered_list.push(page);
     }
 
     Ok(ordered_list)
 }
 
+// The fast path for rules that order every pair in the update. Counting each
+// page's successors gives a total sort key, and the counts only run n-1 down to
+// 0 with no pair ruled both ways when the rules are a total order, so anything
+// else is left to the topological sort to order or explain.
+fn sort_line(rules: &Rules, list: &[i32]) -> Result<Vec<i32>> {
+    let mut counted: Vec<(usize, i32)> = list.iter().map(|&page| (0, page)).collect();
+    let mut both_ways = false;
+    for i in 0..list.len() {
+        for j in i + 1..list.len() {
+            let forward = rules.before(list[i], list[j]);
+            let backward = rules.before(list[j], list[i]);
+            counted[i].0 += forward as usize;
+            counted[j].0 += backward as usize;
+            both_ways |= forward && backward;
+        }
+    }
+    counted.sort_by_key(|&(count, _)| Reverse(count));
+
+    match !both_ways && counted.iter().enumerate().all(|(i, &(count, _))| count == list.len() - 1 - i) {
+        true => Ok(counted.into_iter().map(|(_, page)| page).collect()),
+        false => order_line(rules, list),
+    }
+}
+
 fn part_two(filepath: &str) -> Result<i32> {
     let data = get_input(filepath)?;
 
     let mut sum: i32 = 0;
     for line in data.page_lists {
-        if !check_order(&data.page_map, &line) {
-            let new_line = order_line(&data.page_map, &line)?;
-            sum += find_middle(&new_line);
+        if !check_order(&data.rules, &line) {
+            sum += find_middle(&sort_line(&data.rules, &line)?);
         } 
     }
 
     Ok(sum)
 }
 
+// Benchmark functions
+
+// The MultiMap based check and ordering from before the rules were compiled,
+// kept so the benchmark has something to compare against.
+fn legacy_check_order(page_map: &MultiMap<i32, i32>, list: &[i32]) -> bool {
+    list.windows(2).all(|pair| page_map.get_vec(&pair[0]).is_some_and(|afters| afters.contains(&pair[1])))
+}
+
+fn legacy_order_line(page_map: &MultiMap<i32, i32>, list: &[i32]) -> Vec<i32> {
+    let mut ordered_list = vec![0; list.len()];
+    for first in list {
+        let counter = match page_map.get_vec(first) {
+            Some(afters) => list.iter().filter(|second| afters.contains(second)).count(),
+            None => 0,
+        };
+        ordered_list[list.len() - 1 - counter] = *first;
+    }
+
+    ordered_list
+}
+
+// A small linear congruential generator so the synthetic rules are the same on
+// every run without pulling in a random number crate.
+struct Lcg(u64);
+
+impl Lcg {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+
+    fn shuffle(&mut self, list: &mut [i32]) {
+        for i in (1..list.len()).rev() {
+            list.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+// Every pair of `pages` gets a rule, so each update has exactly one valid order.
+fn synthetic_data(pages: &[i32], updates: usize, seed: u64) -> Data {
+    let mut rng = Lcg(seed);
+    let mut order = pages.to_vec();
+    rng.shuffle(&mut order);
+
+    let mut page_map = MultiMap::new();
+    for (i, &before) in order.iter().enumerate() {
+        for &after in &order[i + 1..] {
+            page_map.insert(before, after);
+        }
+    }
+
+    let page_lists = (0..updates)
+        .map(|_| {
+            let mut update = pages.to_vec();
+            rng.shuffle(&mut update);
+            update.truncate(5 + 2 * rng.below(10));
+            update
+        })
+        .collect();
+
+    let rules = Rules::new(&page_map);
+    Data { page_map, rules, page_lists }
+}
+
+fn time_legacy(data: &Data) -> (i32, Duration) {
+    let now = Instant::now();
+    let sum = data.page_lists
+        .iter()
+        .filter(|line| !legacy_check_order(&data.page_map, line))
+        .map(|line| find_middle(&legacy_order_line(&data.page_map, line)))
+        .sum();
+
+    (sum, now.elapsed())
+}
+
+fn time_compiled(data: &Data) -> Result<(i32, Duration)> {
+    let now = Instant::now();
+    let mut sum = 0;
+    for line in data.page_lists.iter().filter(|line| !check_order(&data.rules, line)) {
+        sum += find_middle(&sort_line(&data.rules, line)?);
+    }
+
+    Ok((sum, now.elapsed()))
+}
+
+fn benchmark(updates: usize) -> Result<String> {
+    let mut output = String::new();
+    let setups = [("two digit pages, bitset", (10..100).collect::<Vec<i32>>()),
+                  ("three digit pages, hashed pairs", (100..190).collect::<Vec<i32>>())];
+
+    for (name, pages) in setups {
+        let data = synthetic_data(&pages, updates, 2024);
+        let (legacy_sum, legacy_time) = time_legacy(&data);
+        let (sum, time) = time_compiled(&data)?;
+        if sum != legacy_sum {
+            bail!("The compiled rules disagree with the MultiMap: {} vs {}", sum, legacy_sum);
+        }
+
+        let rule_count: usize = data.page_map.iter_all().map(|(_, afters)| afters.len()).sum();
+        output.push_str(&format!("{} ({} rules, {} updates)\n", name, rule_count, updates));
+        output.push_str(&format!("    MultiMap lookups: {:.2?}\n", legacy_time));
+        output.push_str(&format!("    Compiled rules:   {:.2?}\n", time));
+    }
+
+    Ok(output)
+}
+
+// Correction explanation functions
+
+fn explain(data: &Data) -> Result<String> {
+    let mut output = String::new();
+    for (i, line) in data.page_lists.iter().enumerate() {
+        if check_order(&data.rules, line) {
+            continue;
+        }
+
+        let target = order_line(&data.rules, line)?;
+        let swaps = edits::adjacent_swaps(line, &target);
+        let moves = edits::page_moves(line, &target);
+        if edits::apply(line, &swaps) != target || edits::apply(line, &moves) != target {
+            bail!("The edits for update {} do not produce its corrected order", i);
+        }
+
+        output.push_str(&format!("Update {}: {} -> {}\n", i, join(line), join(&target)));
+        output.push_str(&format!("    {} adjacent swaps\n", swaps.len()));
+        for swap in &swaps {
+            output.push_str(&format!("        {}\n", swap));
+        }
+        output.push_str(&format!("    {} page moves\n", moves.len()));
+        for page_move in &moves {
+            output.push_str(&format!("        {}\n", page_move));
+        }
+    }
+
+    Ok(output)
+}
+
+// Rule analysis functions
+
+fn join(pages: &[i32]) -> String {
+    pages.iter().map(|page| page.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+fn analyze(data: &Data) -> String {
+    let graph = RuleGraph::new(&data.page_map);
+    let ruled: BTreeSet<i32> = graph.pages().collect();
+    let unruled: BTreeSet<i32> = data.page_lists
+        .iter()
+        .flatten()
+        .filter(|page| !ruled.contains(page))
+        .copied()
+        .collect();
+    let components = graph.strongly_connected_components();
+    let cycles = graph.cycles();
+    let redundant = graph.transitive_redundancies();
+
+    let mut output = format!("{} rules over {} pages\n", graph.rule_count(), ruled.len());
+    output.push_str(&format!("Pages in updates with no rules: {}\n",
+                             join(&unruled.into_iter().collect::<Vec<i32>>())));
+    output.push_str(&format!("Strongly connected components: {}\n", components.len()));
+    for component in components.iter().filter(|component| component.len() > 1) {
+        output.push_str(&format!("    {}\n", join(component)));
+    }
+    output.push_str(&format!("Components containing a cycle: {}\n", cycles.len()));
+    output.push_str(&format!("Transitively redundant rules: {}\n", redundant.len()));
+    for (before, after) in redundant {
+        output.push_str(&format!("    {}|{}\n", before, after));
+    }
+
+    output
+}
+
+fn export_dot(data: &Data, update: Option<usize>) -> Result<String> {
+    let graph = RuleGraph::new(&data.page_map);
+    match update {
+        None => Ok(graph.to_dot("rules")),
+        Some(i) => {
+            let pages = data.page_lists
+                .get(i)
+                .with_context(|| format!("There is no update {}", i))?;
+            Ok(graph.subgraph(pages).to_dot(&format!("update {}", i)))
+        },
+    }
+}
+
 fn main() {
     let filepath = "input.txt";
+    let args: Vec<String> = std::env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        Some("analyze") => {
+            match get_input(filepath) {
+                Ok(data) => print!("{}", analyze(&data)),
+                Err(e) => println!("Error: Could not analyze the rules. {}", e),
+            };
+            return;
+        },
+        Some("bench") => {
+            let updates = args.get(2).and_then(|n| n.parse::<usize>().ok()).unwrap_or(20000);
+            match benchmark(updates) {
+                Ok(output) => print!("{}", output),
+                Err(e) => println!("Error: Could not run the benchmark. {}", e),
+            };
+            return;
+        },
+        Some("explain") => {
+            match get_input(filepath).and_then(|data| explain(&data)) {
+                Ok(output) => print!("{}", output),
+                Err(e) => println!("Error: Could not explain the corrections. {}", e),
+            };
+            return;
+        },
+        Some("dot") => {
+            let update = args.get(2).and_then(|i| i.parse::<usize>().ok());
+            match get_input(filepath).and_then(|data| export_dot(&data, update)) {
+                Ok(dot) => print!("{}", dot),
+                Err(e) => println!("Error: Could not export the rules. {}", e),
+            };
+            return;
+        },
+        _ => (),
+    }
 
     println!("Hello, Advent of Code 2024!");
 
@@ -158,6 +445,36 @@ fn main() {
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47
+";
+
     #[test]
     fn test_main() {
         main();
@@ -175,9 +492,9 @@ mod tests {
         let data = get_input(filepath).unwrap();
         for (i, line) in data.page_lists.into_iter().enumerate() {
             if i < 3 {
-                assert!(check_order(&data.page_map, &line));
+                assert!(check_order(&data.rules, &line));
             } else {
-                assert!(!check_order(&data.page_map, &line));
+                assert!(!check_order(&data.rules, &line));
             }
         }
     }
@@ -193,4 +510,145 @@ mod tests {
         let _filepath = "part_two_test_input.txt";
         assert!(part_two(_filepath).unwrap() == 123); 
     }
+
+    #[test]
+    fn test_order_line() {
+        let data = parse_input(EXAMPLE).unwrap();
+        let ordered: Vec<Vec<i32>> = data.page_lists[3..]
+            .iter()
+            .map(|line| order_line(&data.rules, line).unwrap())
+            .collect();
+        assert_eq!(ordered, vec![vec![97, 75, 47, 61, 53],
+                                 vec![61, 29, 13],
+                                 vec![97, 75, 47, 29, 13]]);
+    }
+
+    #[test]
+    fn test_order_line_errors() {
+        let rules = parse_input("1|2\n2|3\n3|1\n3|4\n\n4,1,2,3,5\n").unwrap();
+        let error = order_line(&rules.rules, &rules.page_lists[0]).unwrap_err();
+        assert_eq!(error.to_string(), "The rules for update [4, 1, 2, 3, 5] contain a cycle: 1 -> 2 -> 3 -> 1");
+
+        let rules = parse_input("1|2\n1|3\n\n3,2,1\n").unwrap();
+        let error = order_line(&rules.rules, &rules.page_lists[0]).unwrap_err();
+        assert_eq!(error.to_string(), "The rules for update [3, 2, 1] leave pages 3 and 2 unordered");
+    }
+
+    #[test]
+    fn test_sort_line_cycle() {
+        // page i goes before the 20 pages after it, wrapping round, so every
+        // neighbouring pair has a rule but the rules as a whole are a cycle
+        let pages: Vec<i32> = (10..=50).collect();
+        let mut input = String::new();
+        for &i in &pages {
+            for &j in &pages {
+                if (1..=20).contains(&(j - i).rem_euclid(41)) {
+                    input.push_str(&format!("{}|{}\n", i, j));
+                }
+            }
+        }
+        let update: Vec<String> = pages.iter().rev().map(|page| page.to_string()).collect();
+        input.push_str(&format!("\n{}\n", update.join(",")));
+
+        let data = parse_input(&input).unwrap();
+        let error = sort_line(&data.rules, &data.page_lists[0]).unwrap_err();
+        assert!(error.to_string().contains("contain a cycle"), "{}", error);
+    }
+
+    #[test]
+    fn test_sort_line_flipped_rules() {
+        // a total order over 29 pages with a few rules reversed is cyclic,
+        // which has to come back as an error rather than a panic from the sort
+        let mut rng = Lcg(7);
+        for _ in 0..300 {
+            let mut order: Vec<i32> = (10..39).collect();
+            rng.shuffle(&mut order);
+            let mut pairs = Vec::new();
+            for (i, &before) in order.iter().enumerate() {
+                for &after in &order[i + 1..] {
+                    pairs.push((before, after));
+                }
+            }
+            for _ in 0..3 {
+                let i = rng.below(pairs.len());
+                pairs[i] = (pairs[i].1, pairs[i].0);
+            }
+
+            let rules = Rules::new(&pairs.into_iter().collect());
+            let error = sort_line(&rules, &order).unwrap_err();
+            assert!(error.to_string().contains("contain a cycle"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_analyze() {
+        let data = parse_input(EXAMPLE).unwrap();
+        let report = analyze(&data);
+        assert!(report.starts_with("21 rules over 7 pages\nPages in updates with no rules: \n"));
+        assert!(report.contains("Strongly connected components: 7\n"));
+        assert!(report.contains("Components containing a cycle: 0\n"));
+        assert!(report.contains("Transitively redundant rules: 15\n"));
+
+        let dot = export_dot(&data, Some(2)).unwrap();
+        assert_eq!(dot, "digraph \"update 2\" {\n    13;\n    29 -> 13;\n    75 -> 13;\n    75 -> 29;\n}\n");
+        assert!(export_dot(&data, Some(6)).is_err());
+    }
+
+    #[test]
+    fn test_explain() {
+        let data = parse_input(EXAMPLE).unwrap();
+        let output = explain(&data).unwrap();
+        assert!(output.starts_with("Update 3: 75, 97, 47, 61, 53 -> 97, 75, 47, 61, 53
+    1 adjacent swaps
+        swap 75 and 97 at positions 0 and 1
+    1 page moves
+        move 75 from position 0 to 1
+Update 4: 61, 13, 29 -> 61, 29, 13
+"));
+        assert!(output.contains("Update 5: 97, 13, 75, 29, 47 -> 97, 75, 47, 29, 13\n    4 adjacent swaps\n"));
+    }
+
+    #[test]
+    fn test_example_answers() {
+        let data = parse_input(EXAMPLE).unwrap();
+        let (correct, incorrect): (Vec<&Vec<i32>>, Vec<&Vec<i32>>) = data.page_lists
+            .iter()
+            .partition(|line| check_order(&data.rules, line));
+        assert_eq!(correct.iter().map(|line| find_middle(line)).sum::<i32>(), 143);
+        assert_eq!(incorrect.iter().map(|line| find_middle(&sort_line(&data.rules, line).unwrap())).sum::<i32>(), 123);
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let output = benchmark(100).unwrap();
+        assert!(output.starts_with("two digit pages, bitset (4005 rules, 100 updates)\n"));
+    }
+
+    #[test]
+    fn test_parse_input_tolerant() {
+        let messy = "# rules\r\n47|53\r\n 97 | 13 # trailing comment\r\n\r\n\r\n\r\n75,47,61 \r\n\r\n97\r\n";
+        let data = parse_input(messy).unwrap();
+        assert_eq!(data.page_map.get_vec(&47), Some(&vec![53]));
+        assert_eq!(data.page_map.get_vec(&97), Some(&vec![13]));
+        assert_eq!(data.page_lists, vec![vec![75, 47, 61], vec![97]]);
+
+        let crlf = EXAMPLE.replace('\n', "\r\n");
+        assert_eq!(parse_input(&crlf).unwrap().page_lists, parse_input(EXAMPLE).unwrap().page_lists);
+    }
+
+    #[test]
+    fn test_parse_input_errors() {
+        let error = parse_input("47|53\n47|x3\n\n75,47,53\n").unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: Could not parse 'x3' to an integer");
+
+        let error = parse_input("47|53\n\n75,47,53\n75,47\n").unwrap_err();
+        assert_eq!(error.to_string(),
+                   "Line 4: The update '75,47' has 2 pages, but needs an odd number to have a middle page");
+
+        let error = parse_input("47|53\n\n75,47,53\n61|13\n").unwrap_err();
+        assert_eq!(error.to_string(), "Line 4: Found the rule '61|13' after the updates had started");
+
+        let error = parse_input("47|53\n\n75,,53\n").unwrap_err();
+        assert_eq!(error.to_string(), "Line 3: Could not parse '' to an integer");
+    }
 }
@@ -0,0 +1,116 @@
+use multimap::MultiMap;
+use std::collections::HashSet;
+use std::hash::{BuildHasherDefault, Hasher};
+
+// The page ordering rules compiled for constant time "must X come before Y"
+// lookups. Puzzle inputs only use two digit pages, so those fit in a 100x100
+// bitset with one u128 row per page; anything else falls back to a hashed set
+// of pairs.
+
+const DENSE_PAGES: i32 = 100;
+
+// Sorting an update looks up every pair of its pages, so the hashed set uses
+// the multiply and rotate hash from rustc instead of the default SipHash. The
+// pairs come from the puzzle input, so there is nothing to harden it against.
+#[derive(Debug, Clone, Copy, Default)]
+struct PairHasher(u64);
+
+impl Hasher for PairHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(byte as u64);
+        }
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write_u64(n as u32 as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x517cc1b727220a95);
+    }
+}
+
+type PairSet = HashSet<(i32, i32), BuildHasherDefault<PairHasher>>;
+
+#[derive(Debug, Clone)]
+enum Precedence {
+    Dense(Box<[u128; DENSE_PAGES as usize]>),
+    Sparse(PairSet),
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    precedence: Precedence,
+}
+
+impl Rules {
+    pub fn new(page_map: &MultiMap<i32, i32>) -> Self {
+        let pairs = page_map
+            .iter_all()
+            .flat_map(|(&before, afters)| afters.iter().map(move |&after| (before, after)));
+        let dense = page_map
+            .iter_all()
+            .all(|(before, afters)| (0..DENSE_PAGES).contains(before)
+                && afters.iter().all(|after| (0..DENSE_PAGES).contains(after)));
+
+        let precedence = match dense {
+            true => {
+                let mut rows = Box::new([0u128; DENSE_PAGES as usize]);
+                for (before, after) in pairs {
+                    rows[before as usize] |= 1 << after;
+                }
+                Precedence::Dense(rows)
+            },
+            false => Precedence::Sparse(pairs.collect()),
+        };
+
+        Rules { precedence }
+    }
+
+    #[cfg(test)]
+    fn is_dense(&self) -> bool {
+        matches!(self.precedence, Precedence::Dense(_))
+    }
+
+    // true if there is a rule `before|after`
+    pub fn before(&self, before: i32, after: i32) -> bool {
+        match &self.precedence {
+            Precedence::Dense(rows) => {
+                (0..DENSE_PAGES).contains(&before)
+                    && (0..DENSE_PAGES).contains(&after)
+                    && rows[before as usize] & (1 << after) != 0
+            },
+            Precedence::Sparse(pairs) => pairs.contains(&(before, after)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dense_rules() {
+        let rules = Rules::new(&[(47, 53), (97, 13), (97, 47), (0, 99)].into_iter().collect());
+        assert!(rules.is_dense());
+        assert!(rules.before(47, 53));
+        assert!(rules.before(0, 99));
+        assert!(!rules.before(53, 47));
+        assert!(!rules.before(-1, 47));
+        assert!(!rules.before(47, 100));
+    }
+
+    #[test]
+    fn test_sparse_rules() {
+        let rules = Rules::new(&[(470, 53), (53, 1300), (470, 1300)].into_iter().collect());
+        assert!(!rules.is_dense());
+        assert!(rules.before(470, 53));
+        assert!(!rules.before(53, 470));
+        assert!(rules.before(53, 1300));
+    }
+}
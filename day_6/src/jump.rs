@@ -0,0 +1,166 @@
+use crate::{Direction, Guard};
+use crate::states::StateSet;
+use grid::Grid;
+use std::collections::HashSet;
+
+// Precomputed jumps for the patrol. For every cell and direction the table
+// holds where the guard would stop, one cell short of the next '#', or None if
+// the guard would walk off the map. The guard then moves from turn to turn
+// instead of square by square, and a single extra obstacle is handled as an
+// overlay on top of the table so it never has to be rebuilt.
+
+pub fn dir_index(dir: Direction) -> usize {
+    match dir {
+        Direction::Up => 0,
+        Direction::Right => 1,
+        Direction::Down => 2,
+        Direction::Left => 3,
+    }
+}
+
+pub struct JumpTable {
+    rows: usize,
+    cols: usize,
+    stops: Vec<[Option<(usize, usize)>; 4]>,
+}
+
+impl JumpTable {
+    pub fn new(data: &Grid<char>) -> Self {
+        let rows = data.rows();
+        let cols = data.cols();
+        let mut table = JumpTable { rows, cols, stops: vec![[None; 4]; rows * cols] };
+
+        for x in 0..rows {
+            table.fill(data, Direction::Right, (0..cols).map(|y| (x, y)).collect());
+            table.fill(data, Direction::Left, (0..cols).rev().map(|y| (x, y)).collect());
+        }
+        for y in 0..cols {
+            table.fill(data, Direction::Down, (0..rows).map(|x| (x, y)).collect());
+            table.fill(data, Direction::Up, (0..rows).rev().map(|x| (x, y)).collect());
+        }
+
+        table
+    }
+
+    // `cells` is one row or column in the order the guard walks it. Working
+    // backwards, a cell stops on itself if the next cell is an obstacle and
+    // otherwise stops wherever the next cell does.
+    fn fill(&mut self, data: &Grid<char>, dir: Direction, cells: Vec<(usize, usize)>) {
+        let mut stop = None;
+        for (i, &cell) in cells.iter().enumerate().rev() {
+            if let Some(&next) = cells.get(i + 1) {
+                if data[next] == '#' {
+                    stop = Some(cell);
+                }
+            }
+            self.stops[cell.0 * self.cols + cell.1][dir_index(dir)] = stop;
+        }
+    }
+
+    // A set of guard states sized for this map.
+    pub fn state_set(&self) -> StateSet {
+        StateSet::new(self.rows, self.cols)
+    }
+
+    pub fn stop(&self, x: usize, y: usize, dir: Direction) -> Option<(usize, usize)> {
+        self.stops[x * self.cols + y][dir_index(dir)]
+    }
+
+    // Where the guard stops with `extra` standing in as one more obstacle: if it
+    // lies on the path before the table's stop, the guard stops just short of it.
+    pub fn stop_with(&self, x: usize, y: usize, dir: Direction, extra: Option<(usize, usize)>) -> Option<(usize, usize)> {
+        let stop = self.stop(x, y, dir);
+        let Some((ex, ey)) = extra else { return stop };
+
+        let ahead = match dir {
+            Direction::Up => ey == y && ex < x && stop.is_none_or(|(sx, _)| ex >= sx),
+            Direction::Down => ey == y && ex > x && stop.is_none_or(|(sx, _)| ex <= sx),
+            Direction::Left => ex == x && ey < y && stop.is_none_or(|(_, sy)| ey >= sy),
+            Direction::Right => ex == x && ey > y && stop.is_none_or(|(_, sy)| ey <= sy),
+        };
+        if !ahead {
+            return stop;
+        }
+
+        match dir {
+            Direction::Up => Some((ex + 1, ey)),
+            Direction::Down => Some((ex - 1, ey)),
+            Direction::Left => Some((ex, ey + 1)),
+            Direction::Right => Some((ex, ey - 1)),
+        }
+    }
+}
+
+// Jumps from turn to turn and reports whether the guard ends up in a loop. Only
+// the states at turns are remembered, which is enough to spot a repeat. `seen`
+// is cleared first, so one set can be reused for every candidate.
+pub fn detect_loop(table: &JumpTable, guard: &Guard, extra: Option<(usize, usize)>, seen: &mut StateSet) -> bool {
+    let mut guard = *guard;
+    seen.reset();
+
+    while let Some((x, y)) = table.stop_with(guard.x, guard.y, guard.dir, extra) {
+        guard.x = x;
+        guard.y = y;
+        guard.turn_right();
+        if !seen.insert(&guard) {
+            return true;
+        }
+    }
+
+    false
+}
+
+// The same check with a fresh hashed set every time, which is what
+// detect_loop's bitset replaced.
+pub fn detect_loop_hashed(table: &JumpTable, guard: &Guard, extra: Option<(usize, usize)>) -> bool {
+    let mut guard = *guard;
+    let mut turns: HashSet<(usize, usize, Direction)> = HashSet::new();
+
+    while let Some((x, y)) = table.stop_with(guard.x, guard.y, guard.dir, extra) {
+        guard.x = x;
+        guard.y = y;
+        guard.turn_right();
+        if !turns.insert((guard.x, guard.y, guard.dir)) {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grid;
+
+    #[test]
+    fn test_jump_table() {
+        let data = parse_grid(&["..#.", "....", "#...", "...#"], &['#']).unwrap();
+        let table = JumpTable::new(&data);
+
+        assert_eq!(table.stop(1, 2, Direction::Up), Some((1, 2)));
+        assert_eq!(table.stop(3, 2, Direction::Up), Some((1, 2)));
+        assert_eq!(table.stop(1, 0, Direction::Down), Some((1, 0)));
+        assert_eq!(table.stop(3, 0, Direction::Right), Some((3, 2)));
+        assert_eq!(table.stop(2, 3, Direction::Left), Some((2, 1)));
+        assert_eq!(table.stop(1, 1, Direction::Right), None);
+        assert_eq!(table.stop(1, 1, Direction::Up), None);
+        for dir in [Direction::Up, Direction::Right, Direction::Down, Direction::Left] {
+            assert_eq!(table.stop(1, 1, dir), table.stop_with(1, 1, dir, None));
+        }
+    }
+
+    #[test]
+    fn test_stop_with_extra() {
+        let data = parse_grid(&["..#.", "....", "#...", "...#"], &['#']).unwrap();
+        let table = JumpTable::new(&data);
+
+        // extra obstacle before the table's stop
+        assert_eq!(table.stop_with(3, 0, Direction::Right, Some((3, 2))), Some((3, 1)));
+        // extra obstacle on a run that would otherwise leave the map
+        assert_eq!(table.stop_with(1, 0, Direction::Right, Some((1, 3))), Some((1, 2)));
+        // extra obstacle behind the guard or past the stop is ignored
+        assert_eq!(table.stop_with(1, 2, Direction::Right, Some((1, 0))), None);
+        assert_eq!(table.stop_with(3, 2, Direction::Up, Some((0, 2))), Some((1, 2)));
+    }
+}
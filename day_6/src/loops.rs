@@ -0,0 +1,95 @@
+use crate::Guard;
+use grid::Grid;
+use std::collections::{BTreeMap, BTreeSet};
+
+// What a loop looks like once the guard is caught in one: how many steps in it
+// started, how many moves and turns one lap takes, and which cells it covers.
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Cycle {
+    // moves and turns taken before the guard first stood in the loop
+    pub entry: usize,
+    // moves and turns in one lap
+    pub length: usize,
+    // cells the guard stands on during a lap, in reading order
+    pub cells: Vec<(usize, usize)>,
+}
+
+// Walks one lap from `start`, a state the guard is known to come back to, which
+// it first reached after `entry` steps.
+pub fn trace(data: &Grid<char>, extra: Option<(usize, usize)>, start: Guard, entry: usize) -> Cycle {
+    let mut guard = start;
+    let mut cells = BTreeSet::from([(guard.x, guard.y)]);
+    let mut length = 0;
+
+    loop {
+        crate::step(data, extra, &mut guard).expect("the guard left the map while tracing a loop");
+        length += 1;
+        cells.insert((guard.x, guard.y));
+
+        if guard == start {
+            break;
+        }
+    }
+
+    Cycle { entry, length, cells: cells.into_iter().collect() }
+}
+
+// Distribution of loop lengths over every obstacle that causes one, plus the
+// spread of entry steps and loop sizes.
+pub fn summarize(cycles: &[((usize, usize), Cycle)]) -> String {
+    let mut output = format!("{} obstacle positions cause a loop\n", cycles.len());
+    if cycles.is_empty() {
+        return output;
+    }
+
+    let mut lengths: BTreeMap<usize, usize> = BTreeMap::new();
+    for (_, cycle) in cycles {
+        *lengths.entry(cycle.length).or_default() += 1;
+    }
+
+    let entries = cycles.iter().map(|(_, cycle)| cycle.entry);
+    let sizes = cycles.iter().map(|(_, cycle)| cycle.cells.len());
+    output.push_str(&format!(
+        "entered after {} to {} steps, covering {} to {} cells\n",
+        entries.clone().min().unwrap_or(0),
+        entries.max().unwrap_or(0),
+        sizes.clone().min().unwrap_or(0),
+        sizes.max().unwrap_or(0),
+    ));
+
+    output.push_str("loop length: obstacles\n");
+    for (length, count) in lengths {
+        output.push_str(&format!("    {:>6}: {}\n", length, count));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_grid, Direction};
+
+    #[test]
+    fn test_trace() {
+        let data = parse_grid(&[".#..", "...#", "#...", "..#."], &['#']).unwrap();
+        let start = Guard { x: 1, y: 1, dir: Direction::Right };
+        let cycle = trace(&data, None, start, 2);
+        assert_eq!(cycle.entry, 2);
+        assert_eq!(cycle.length, 8);
+        assert_eq!(cycle.cells, vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let cycle = |entry, length, size| Cycle { entry, length, cells: vec![(0, 0); size] };
+        let cycles = [((0, 1), cycle(3, 8, 4)), ((2, 3), cycle(10, 20, 12)), ((4, 5), cycle(5, 8, 4))];
+        assert_eq!(summarize(&cycles), "3 obstacle positions cause a loop\n\
+                                        entered after 3 to 10 steps, covering 4 to 12 cells\n\
+                                        loop length: obstacles\n\
+                                        \x20        8: 2\n\
+                                        \x20       20: 1\n");
+        assert_eq!(summarize(&[]), "0 obstacle positions cause a loop\n");
+    }
+}
@@ -1,9 +1,23 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use grid::Grid;
-use std::time::Instant;
-use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet};
+use std::thread;
+
+#[cfg(feature = "count-allocs")]
+mod counting;
+mod jump;
+mod loops;
+mod patrol;
+mod replay;
+mod states;
+
+use jump::JumpTable;
+use loops::Cycle;
+use patrol::{Effect, Rules, Status, TurnPolicy};
+use replay::{Replay, Trail};
 
 // convenience ENUMS and structs
 
@@ -32,6 +46,24 @@ impl Guard {
         }
     }
 
+    fn turn_left(&mut self) {
+        self.dir = match self.dir {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    fn turn_around(&mut self) {
+        self.dir = match self.dir {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
     fn get_forward_coords(&self) -> (Option<usize>, Option<usize>) {
         match self.dir {
             Direction::Up    => (self.x.checked_sub(1), Some(self.y)),
@@ -41,159 +73,482 @@ impl Guard {
         }
     }
 
-    fn move_forward(&mut self) -> Result<()> {
+    // The cell in front of the guard, if that is still on the map.
+    fn ahead(&self, data: &Grid<char>) -> Option<(usize, usize)> {
         match self.get_forward_coords() {
-            (Some(x), Some(y)) => {
-                self.x = x;
-                self.y = y;
-                Ok(())
-            },
-            _ => Err(anyhow::anyhow!("Tried to move out of bounds too a negative coordinate")),
+            (Some(x), Some(y)) if x < data.rows() && y < data.cols() => Some((x, y)),
+            _ => None,
         }
     }
 }
 
+// One move or turn of the puzzle's guard: forward if the cell ahead is free,
+// otherwise a turn to the right. `extra` is one more obstacle on top of the
+// grid. None once the guard would walk off the map.
+fn step(data: &Grid<char>, extra: Option<(usize, usize)>, guard: &mut Guard) -> Option<()> {
+    let (nx, ny) = guard.ahead(data)?;
+    match data[(nx, ny)] == '#' || extra == Some((nx, ny)) {
+        true => guard.turn_right(),
+        false => {
+            guard.x = nx;
+            guard.y = ny;
+        },
+    }
+
+    Some(())
+}
+
 // data cleaning functions
 
-fn parse_data(filepath: &str) -> Result<Grid<char>> {
+// `obstacles` are the characters that may appear on the map besides '.' and
+// the guard arrows. The puzzle itself only has '#'.
+fn parse_data(filepath: &str, obstacles: &[char]) -> Result<Grid<char>> {
     let file = File::open(filepath)?;
     let lines: Vec<String> = BufReader::new(file)
         .lines()
         .collect::<Result<_, _>>()?;
 
-    let rows = lines.len();
-    let cols = lines.get(0).map_or(0, |line| line.len());
-
-    let mut data = Vec::with_capacity(rows * cols);
-    for line in lines {
-        data.extend(line.chars());
-    }
-
-    let grid = Grid::from_vec(data, rows);
-    Ok(grid)
+    parse_grid(&lines, obstacles)
 }
 
-// part one functions
+fn parse_grid<S: AsRef<str>>(lines: &[S], obstacles: &[char]) -> Result<Grid<char>> {
+    // blank lines at the end of the file are not part of the map
+    let end = lines.iter().rposition(|line| !line.as_ref().is_empty()).map_or(0, |i| i + 1);
+    let lines = &lines[..end];
+    let cols = match lines.first() {
+        Some(line) => line.as_ref().chars().count(),
+        None => bail!("The map is empty"),
+    };
 
-fn find_guard_start(data: &mut Grid<char>) -> Guard {
-    let mut guard_cords: (usize, usize) = (0, 0);
-    let mut guard_direction: Direction = Direction::Up;
-    let mut guard_found = None;
+    let mut data = Vec::with_capacity(lines.len() * cols);
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.as_ref();
+        let width = line.chars().count();
+        if width != cols {
+            bail!("Line {}: The map row is {} characters wide, but the first row is {}", i + 1, width, cols);
+        }
 
-    for (cord, val) in data.indexed_iter() {
-        match val {
-            '^' => {
-                guard_found = Some((cord, Direction::Up));
-                break;
-            },
-            '>' => {
-                guard_found = Some((cord, Direction::Right));
-                break;
-            },
-            'v' => {
-                guard_found = Some((cord, Direction::Down));
-                break;
-            },
-            '<' => {
-                guard_found = Some((cord, Direction::Left));
-                break;
-            },
-            _ => (),
+        for (j, c) in line.chars().enumerate() {
+            if !matches!(c, '.' | '^' | '>' | 'v' | '<') && !obstacles.contains(&c) {
+                bail!("Line {}, column {}: Unknown map character '{}'", i + 1, j + 1, c);
+            }
+            data.push(c);
         }
     }
 
-    if let Some((cords, direction)) = guard_found {
-        guard_cords = cords;
-        guard_direction = direction;
-        data[guard_cords] = '.';
-    }
+    Ok(Grid::from_vec(data, cols))
+}
 
-    let guard = Guard {
-        x: guard_cords.0,
-        y: guard_cords.1,
-        dir: guard_direction,
-    };
+// part one functions
 
-    guard
+fn find_guard_start(data: &mut Grid<char>) -> Result<Guard> {
+    let guards = find_guards(data);
+    match guards.as_slice() {
+        [guard] => Ok(*guard),
+        [] => bail!("There is no guard (^, >, v or <) on the map"),
+        [first, second, ..] => bail!("There are {} guards on the map, the first two at ({}, {}) and ({}, {}), but only one was expected",
+                                     guards.len(), first.x, first.y, second.x, second.y),
+    }
 }
 
-fn simulate(data: &Grid<char>, guard: &Guard, detect_loop: bool) -> Result<(HashSet<(usize, usize)>, bool)> {
-    let rows = data.rows();
-    let cols = data.cols();
-    let mut guard = guard.clone();
+// the cells the guard visited, and the loop it got stuck in if any
+type Walk = (HashSet<(usize, usize)>, Option<Cycle>);
+
+// `extra` is one more obstacle on top of the grid, so the grid itself never
+// has to change while candidates are tried. With `detect_loop` set, a guard
+// that gets stuck comes back with a description of the loop it is stuck in.
+fn simulate(data: &Grid<char>, guard: &Guard, extra: Option<(usize, usize)>, detect_loop: bool) -> Walk {
+    let mut guard = *guard;
     let mut visited: HashSet<(usize, usize)> = HashSet::new();
     visited.insert((guard.x, guard.y));
 
-    let mut visited_states = HashSet::new();
+    // every state seen so far and the step it was first seen at
+    let mut visited_states: HashMap<Guard, usize> = HashMap::new();
     if detect_loop {
-        visited_states.insert((guard.x, guard.y, guard.dir));
+        visited_states.insert(guard, 0);
     }
 
-    loop {
-        let (nx, ny) = match guard.get_forward_coords() {
-            (Some(x), Some(y)) => (x, y),
-            _ => break
-        };
-
-        if nx >= rows || ny >= cols {
-            break;
-        }
-
-        match data[(nx, ny)] {
-            '#' => guard.turn_right(),
-            _ => {
-                guard.move_forward()?;
-                visited.insert((guard.x, guard.y));
-            }
-        }
+    while step(data, extra, &mut guard).is_some() {
+        visited.insert((guard.x, guard.y));
 
         if detect_loop {
-            let state = (guard.x, guard.y, guard.dir);
-            match visited_states.contains(&state) {
-                true => return Ok((visited, true)),
-                false => visited_states.insert(state),
+            let step = visited_states.len();
+            match visited_states.get(&guard) {
+                Some(&entry) => return (visited, Some(loops::trace(data, extra, guard, entry))),
+                None => visited_states.insert(guard, step),
             };
         }
     }
-    Ok((visited, false))
+    (visited, None)
 }
 
 fn part_one(filepath: &str) -> Result<usize> {
-    let mut data = parse_data(filepath)?;
-    let guard = find_guard_start(&mut data);
-    let (visited, _) = simulate(&data, &guard, false)?;
+    let mut data = parse_data(filepath, &['#'])?;
+    let guard = find_guard_start(&mut data)?;
+    let (visited, _) = simulate(&data, &guard, None, false);
     Ok(visited.len())
 }
 
+// patrol variants
+
+// Every guard on the map in reading order, each replaced by '.'.
+fn find_guards(data: &mut Grid<char>) -> Vec<Guard> {
+    let mut guards = Vec::new();
+    for ((x, y), val) in data.indexed_iter() {
+        let dir = match val {
+            '^' => Direction::Up,
+            '>' => Direction::Right,
+            'v' => Direction::Down,
+            '<' => Direction::Left,
+            _ => continue,
+        };
+        guards.push(Guard { x, y, dir });
+    }
+
+    for guard in &guards {
+        data[(guard.x, guard.y)] = '.';
+    }
+
+    guards
+}
+
+// `obstacle` is written as C=effect, for example `~=reverse`.
+fn parse_obstacle(rules: Rules, obstacle: &str) -> Result<Rules> {
+    let mut chars = obstacle.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), Some('=')) => Ok(rules.with_effect(c, Effect::parse(chars.as_str())?)),
+        _ => bail!("Obstacles are given as C=effect, not '{}'", obstacle),
+    }
+}
+
+fn run_patrol(data: &Grid<char>, rules: &Rules) -> String {
+    let mut data = data.clone();
+    let guards = find_guards(&mut data);
+    let outcome = patrol::run(&data, &guards, rules);
+
+    let mut output = format!("{} cells visited in {} ticks\n", outcome.visited.len(), outcome.ticks);
+    for (i, agent) in outcome.agents.iter().enumerate() {
+        let status = match (outcome.looped, agent.status) {
+            (_, Status::Halted) => "halted",
+            (_, Status::Exited) => "left the map",
+            (true, Status::Walking) => "stuck in a loop",
+            (false, Status::Walking) => "still walking",
+        };
+        output.push_str(&format!("    guard {} {} at ({}, {})\n", i, status, agent.guard.x, agent.guard.y));
+    }
+
+    output
+}
+
 // part two functions
 
+// Every cell the guard walks through on the unmodified map, except the start,
+// is a place a new obstacle could change the route.
+fn candidates(data: &Grid<char>, guard: &Guard) -> Vec<(usize, usize)> {
+    let (visited, _) = simulate(data, guard, None, false);
+    visited.into_iter().filter(|&cell| cell != (guard.x, guard.y)).collect()
+}
+
+fn threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+// The candidates are split into one chunk per thread. Every thread only reads
+// the grid and the jump table, so they can share them without locking.
+fn count_loops(data: &Grid<char>, guard: &Guard, threads: usize) -> Result<i32> {
+    let table = JumpTable::new(data);
+    let candidates = candidates(data, guard);
+    let chunk_size = candidates.len().div_ceil(threads.max(1)).max(1);
+
+    let loops: usize = thread::scope(|scope| {
+        let workers: Vec<_> = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let table = &table;
+                scope.spawn(move || {
+                    let mut seen = table.state_set();
+                    chunk
+                        .iter()
+                        .filter(|&&cell| jump::detect_loop(table, guard, Some(cell), &mut seen))
+                        .count()
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("loop check thread panicked"))
+            .sum()
+    });
+
+    Ok(loops as i32)
+}
+
 fn part_two(filepath: &str) -> Result<i32> {
-    let mut data = parse_data(filepath)?;
-    let guard = find_guard_start(&mut data);
+    let mut data = parse_data(filepath, &['#'])?;
+    let guard = find_guard_start(&mut data)?;
+    count_loops(&data, &guard, threads())
+}
 
-    let (visited, _) = simulate(&data, &guard, false)?;
-    let mut loop_count = 0;
+// loop analysis
 
-    for &(vx, vy) in &visited {
-        if (vx, vy) == (guard.x, guard.y) {
-            continue;
+// The loop each trapping obstacle causes, in the order of loop_obstacles.
+fn loop_cycles(data: &Grid<char>, guard: &Guard) -> Result<Vec<((usize, usize), Cycle)>> {
+    let mut cycles = Vec::new();
+    for obstacle in loop_obstacles(data, guard)? {
+        match simulate(data, guard, Some(obstacle), true) {
+            (_, Some(cycle)) => cycles.push((obstacle, cycle)),
+            (_, None) => bail!("The obstacle at {:?} was expected to cause a loop", obstacle),
         }
+    }
+
+    Ok(cycles)
+}
 
-        if data[(vx, vy)] == '.' {
-            data[(vx, vy)] = '#';
-            let (_, loop_detected) = simulate(&data, &guard, true)?;
-            if loop_detected {
+fn describe_loops(filepath: &str, each: bool) -> Result<String> {
+    let mut data = parse_data(filepath, &['#'])?;
+    let guard = find_guard_start(&mut data)?;
+    let cycles = loop_cycles(&data, &guard)?;
+
+    let mut output = loops::summarize(&cycles);
+    if each {
+        for ((x, y), cycle) in &cycles {
+            output.push_str(&format!(
+                "obstacle at ({}, {}): entered after {} steps, {} steps around, {} cells\n",
+                x, y, cycle.entry, cycle.length, cycle.cells.len(),
+            ));
+        }
+    }
+
+    Ok(output)
+}
+
+// replay functions
+
+// Every obstacle position that traps the guard, in reading order.
+fn loop_obstacles(data: &Grid<char>, guard: &Guard) -> Result<Vec<(usize, usize)>> {
+    let table = JumpTable::new(data);
+    let mut seen = table.state_set();
+    let mut obstacles: Vec<(usize, usize)> = candidates(data, guard)
+        .into_iter()
+        .filter(|&cell| jump::detect_loop(&table, guard, Some(cell), &mut seen))
+        .collect();
+    obstacles.sort();
+
+    Ok(obstacles)
+}
+
+// Replays part one, or with `obstacle` set the loop caused by the obstacle at
+// that index of loop_obstacles.
+fn record_replay(filepath: &str, obstacle: Option<usize>) -> Result<Replay> {
+    let mut data = parse_data(filepath, &['#'])?;
+    let guard = find_guard_start(&mut data)?;
+
+    let extra = match obstacle {
+        Some(index) => {
+            let obstacles = loop_obstacles(&data, &guard)?;
+            match obstacles.get(index) {
+                Some(&cell) => Some(cell),
+                None => bail!("There are only {} obstacle positions that cause a loop", obstacles.len()),
+            }
+        },
+        None => None,
+    };
+
+    Ok(Replay::record(&data, &guard, extra))
+}
+
+// benchmark functions
+
+// Allocations made so far, if they are being counted at all.
+fn allocations() -> Option<usize> {
+    #[cfg(feature = "count-allocs")]
+    return Some(counting::allocations());
+    #[cfg(not(feature = "count-allocs"))]
+    None
+}
+
+const RUNS: usize = 7;
+
+// Runs `f` once to warm up, counting its allocations with the count-allocs
+// feature, then reports the median time of RUNS more runs.
+fn measure<T>(mut f: impl FnMut() -> T) -> (T, Duration, Option<usize>) {
+    let before = allocations();
+    let result = f();
+    let allocated = allocations().zip(before).map(|(after, before)| after - before);
+
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let now = Instant::now();
+            f();
+            now.elapsed()
+        })
+        .collect();
+    times.sort();
+
+    (result, times[RUNS / 2], allocated)
+}
+
+// The jump table sweep with a new hashed set of states for every candidate,
+// as it was before the bitset.
+fn hashed_count_loops(data: &Grid<char>, guard: &Guard) -> Result<i32> {
+    let table = JumpTable::new(data);
+    let loops = candidates(data, guard)
+        .into_iter()
+        .filter(|&cell| jump::detect_loop_hashed(&table, guard, Some(cell)))
+        .count();
+
+    Ok(loops as i32)
+}
+
+// The original part two: walk the guard square by square for each candidate
+// obstacle, remembering every state until one comes round again.
+fn stepwise_count_loops(data: &Grid<char>, guard: &Guard) -> Result<i32> {
+    let mut loop_count = 0;
+
+    for cell in candidates(data, guard) {
+        let mut walker = *guard;
+        let mut states = HashSet::from([walker]);
+        while step(data, Some(cell), &mut walker).is_some() {
+            if !states.insert(walker) {
                 loop_count += 1;
+                break;
             }
-            data[(vx, vy)] = '.';
         }
     }
 
     Ok(loop_count)
 }
 
+// A square map with roughly one obstacle in `density` cells and the guard in
+// the middle facing up. Each cell hashes its own index with the seed, so a map
+// of the same size always has the same obstacles. Puzzle inputs always let the
+// guard walk off the map, so seeds that trap it are skipped.
+fn synthetic_grid(size: usize, density: usize, seed: u64) -> (Grid<char>, Guard) {
+    let guard = Guard { x: size / 2, y: size / 2, dir: Direction::Up };
+    for seed in seed.. {
+        let mut data = Grid::init(size, size, '.');
+        for (index, cell) in data.iter_mut().enumerate() {
+            let hash = ((index as u64).wrapping_mul(0x9e3779b97f4a7c15) ^ seed).wrapping_mul(0xbf58476d1ce4e5b9);
+            if ((hash >> 32) as usize).is_multiple_of(density) {
+                *cell = '#';
+            }
+        }
+        data[(guard.x, guard.y)] = '.';
+
+        if simulate(&data, &guard, None, true).1.is_none() {
+            return (data, guard);
+        }
+    }
+    unreachable!("every seed traps the guard")
+}
+
+fn benchmark(size: usize) -> Result<String> {
+    let (data, guard) = synthetic_grid(size, 12, 2024);
+    let threads = threads();
+
+    let (stepwise, stepwise_time, stepwise_allocations) = measure(|| stepwise_count_loops(&data, &guard));
+    let (hashed, hashed_time, hashed_allocations) = measure(|| hashed_count_loops(&data, &guard));
+    let (jumped, jump_time, jump_allocations) = measure(|| count_loops(&data, &guard, 1));
+    let (parallel, parallel_time, parallel_allocations) = measure(|| count_loops(&data, &guard, threads));
+    let (stepwise, hashed, jumped, parallel) = (stepwise?, hashed?, jumped?, parallel?);
+
+    if stepwise != hashed {
+        bail!("The jump table disagrees with the step by step walk: {} vs {}", hashed, stepwise);
+    }
+    if jumped != hashed {
+        bail!("The bitset of states disagrees with the hashed one: {} vs {}", jumped, hashed);
+    }
+    if parallel != jumped {
+        bail!("The threaded sweep disagrees with the single threaded one: {} vs {}", parallel, jumped);
+    }
+
+    let mut output = format!("{}x{} map, {} loop positions, median of {} runs\n", size, size, jumped, RUNS);
+    let rows = [("Step by step".to_string(), stepwise_time, stepwise_allocations),
+                ("Jump table, hashed states".to_string(), hashed_time, hashed_allocations),
+                ("Jump table, state bitset".to_string(), jump_time, jump_allocations),
+                (format!("Bitset, {} threads", threads), parallel_time, parallel_allocations)];
+    for (name, time, allocations) in rows {
+        let name = format!("{}:", name);
+        match allocations {
+            Some(allocations) => output.push_str(&format!("    {:<27} {:>10.2?} {:>9} allocations\n", name, time, allocations)),
+            None => output.push_str(&format!("    {:<27} {:>10.2?}\n", name, time)),
+        }
+    }
+    if allocations().is_none() {
+        output.push_str("Build with --features count-allocs to count allocations too\n");
+    }
+
+    Ok(output)
+}
+
 fn main() {
     let filepath = "input.txt";
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("replay") {
+        let option = |name: &str| args
+            .iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1));
+        let obstacle = match args.iter().any(|arg| arg == "--part-two") {
+            true => Some(option("--part-two").and_then(|i| i.parse::<usize>().ok()).unwrap_or(0)),
+            false => None,
+        };
+        let trail = match args.iter().any(|arg| arg == "--marks") {
+            true => Trail::Marks,
+            false => Trail::Lines,
+        };
+        let delay = Duration::from_millis(option("--delay").and_then(|ms| ms.parse::<u64>().ok()).unwrap_or(50));
+        let stride = option("--stride").and_then(|n| n.parse::<usize>().ok()).unwrap_or(1);
+
+        let result = record_replay(filepath, obstacle).and_then(|replay| match option("--cast") {
+            Some(path) => Ok(std::fs::write(path, replay.to_asciicast(trail, delay, stride))?),
+            None => {
+                replay.play(trail, delay, stride);
+                Ok(())
+            },
+        });
+        if let Err(e) = result {
+            println!("Error: Could not replay the patrol. {}", e);
+        }
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("patrol") {
+        let mut rules = Ok(Rules::puzzle());
+        for pair in args.windows(2) {
+            rules = match pair[0].as_str() {
+                "--turn" => rules.and_then(|rules| Ok(rules.with_policy(TurnPolicy::parse(&pair[1])?))),
+                "--obstacle" => rules.and_then(|rules| parse_obstacle(rules, &pair[1])),
+                _ => rules,
+            };
+        }
+
+        match rules.and_then(|rules| Ok(run_patrol(&parse_data(filepath, &rules.obstacles())?, &rules))) {
+            Ok(output) => print!("{}", output),
+            Err(e) => println!("Error: Could not run the patrol. {}", e),
+        };
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("loops") {
+        let each = args.iter().any(|arg| arg == "--each");
+        match describe_loops(filepath, each) {
+            Ok(output) => print!("{}", output),
+            Err(e) => println!("Error: Could not analyze the loops. {}", e),
+        };
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let size = args.get(2).and_then(|n| n.parse::<usize>().ok()).unwrap_or(130);
+        match benchmark(size) {
+            Ok(output) => print!("{}", output),
+            Err(e) => println!("Error: Could not run the benchmark. {}", e),
+        };
+        return;
+    }
 
     println!("Hello, Advent of Code 2024!");
     
@@ -219,6 +574,19 @@ fn main() {
 mod tests {
     use super::*;
 
+    const EXAMPLE: [&str; 10] = [
+        "....#.....",
+        ".........#",
+        "..........",
+        "..#.......",
+        ".......#..",
+        "..........",
+        ".#..^.....",
+        "........#.",
+        "#.........",
+        "......#...",
+    ];
+
     #[test]
     fn test_part_one() {
         let filepath = "part_one_test_input.txt";
@@ -230,4 +598,95 @@ mod tests {
         let filepath = "part_two_test_input.txt";
         assert_eq!(part_two(filepath).unwrap(), 6);
     }
+
+    #[test]
+    fn test_example_answers() {
+        let mut data = parse_grid(&EXAMPLE, &['#']).unwrap();
+        let guard = find_guard_start(&mut data).unwrap();
+        assert_eq!(simulate(&data, &guard, None, false).0.len(), 41);
+        assert_eq!(stepwise_count_loops(&data, &guard).unwrap(), 6);
+        for threads in [1, 2, 3, 64] {
+            assert_eq!(count_loops(&data, &guard, threads).unwrap(), 6);
+        }
+    }
+
+    #[test]
+    fn test_loop_obstacles() {
+        let mut data = parse_grid(&EXAMPLE, &['#']).unwrap();
+        let guard = find_guard_start(&mut data).unwrap();
+        let obstacles = loop_obstacles(&data, &guard).unwrap();
+        assert_eq!(obstacles, vec![(6, 3), (7, 6), (7, 7), (8, 1), (8, 3), (9, 7)]);
+
+        let replay = Replay::record(&data, &guard, Some(obstacles[0]));
+        assert!(replay.loop_length().is_some());
+        assert!(replay.render(replay.frames() - 1, Trail::Lines, false).lines().nth(6).unwrap().starts_with(".#.O^"));
+    }
+
+    #[test]
+    fn test_patrol_variants() {
+        let data = parse_grid(&EXAMPLE, &['#']).unwrap();
+        assert_eq!(run_patrol(&data, &Rules::puzzle()), "41 cells visited in 55 ticks\n    guard 0 left the map at (9, 7)\n");
+
+        let rules = parse_obstacle(Rules::puzzle(), "#=reverse").unwrap();
+        assert!(run_patrol(&data, &rules).contains("guard 0 left the map"));
+        assert!(parse_obstacle(Rules::puzzle(), "#reverse").is_err());
+        assert!(parse_obstacle(Rules::puzzle(), "#=sideways").is_err());
+
+        let mut two = EXAMPLE.map(String::from);
+        two[0] = "....#....v".to_string();
+        let output = run_patrol(&parse_grid(&two, &['#']).unwrap(), &Rules::puzzle());
+        assert!(output.contains("guard 0 ") && output.contains("guard 1 "));
+    }
+
+    #[test]
+    fn test_loop_cycles() {
+        let mut data = parse_grid(&EXAMPLE, &['#']).unwrap();
+        let guard = find_guard_start(&mut data).unwrap();
+        let cycles = loop_cycles(&data, &guard).unwrap();
+        assert_eq!(cycles.len(), 6);
+
+        // the puzzle's first example, an obstacle right next to the guard
+        let (obstacle, cycle) = &cycles[0];
+        assert_eq!(*obstacle, (6, 3));
+        assert_eq!(cycle.entry, 0);
+        assert_eq!(cycle.length, 22);
+        assert_eq!(cycle.cells.len(), 18);
+        assert!(cycle.cells.contains(&(6, 4)) && cycle.cells.contains(&(1, 4)));
+
+        let replay = Replay::record(&data, &guard, Some(*obstacle));
+        assert_eq!(replay.loop_length(), Some(cycle.length));
+    }
+
+    #[test]
+    fn test_parse_grid_errors() {
+        let error = |lines: &[&str]| parse_grid(lines, &['#']).unwrap_err().to_string();
+        assert_eq!(error(&[]), "The map is empty");
+        assert_eq!(error(&["....", "...", "...."]), "Line 2: The map row is 3 characters wide, but the first row is 4");
+        assert_eq!(error(&["..#.", ".x.."]), "Line 2, column 2: Unknown map character 'x'");
+
+        // trailing blank lines are fine
+        let data = parse_grid(&["..#.", ".^..", "", ""], &['#']).unwrap();
+        assert_eq!((data.rows(), data.cols()), (2, 4));
+        assert!(parse_grid(&["..L."], &['#', 'L']).is_ok());
+    }
+
+    #[test]
+    fn test_find_guard_start_errors() {
+        let mut data = parse_grid(&["..#.", "...."], &['#']).unwrap();
+        assert_eq!(find_guard_start(&mut data).unwrap_err().to_string(), "There is no guard (^, >, v or <) on the map");
+
+        let mut data = parse_grid(&["..#>", "^..v"], &['#']).unwrap();
+        assert_eq!(find_guard_start(&mut data).unwrap_err().to_string(),
+                   "There are 3 guards on the map, the first two at (0, 3) and (1, 0), but only one was expected");
+
+        let mut data = parse_grid(&["..#.", ".<.."], &['#']).unwrap();
+        assert_eq!(find_guard_start(&mut data).unwrap(), Guard { x: 1, y: 1, dir: Direction::Left });
+        assert_eq!(data[(1, 1)], '.');
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let output = benchmark(30).unwrap();
+        assert!(output.starts_with("30x30 map"));
+    }
 }
@@ -0,0 +1,257 @@
+use crate::Guard;
+use anyhow::{bail, Result};
+use grid::Grid;
+use std::collections::{HashMap, HashSet};
+
+// A more general patrol than the puzzle's: guards can turn by different
+// policies, obstacle characters can have different effects, and several guards
+// can walk at once. Guards move in lockstep, one action each per tick, and treat
+// a cell another guard stood on at the start of the tick as blocked.
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Turn {
+    Right,
+    Left,
+    Reverse,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TurnPolicy {
+    Right,
+    Left,
+    Reverse,
+    // right on the first turn, left on the next, and so on
+    Alternating,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Effect {
+    // turn the way the guard's policy says
+    Block,
+    // turn this way whatever the policy
+    Force(Turn),
+    // the guard stops walking where it stands
+    Halt,
+}
+
+impl TurnPolicy {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "right" => Ok(TurnPolicy::Right),
+            "left" => Ok(TurnPolicy::Left),
+            "reverse" => Ok(TurnPolicy::Reverse),
+            "alternating" => Ok(TurnPolicy::Alternating),
+            _ => bail!("Unknown turn policy '{}', expected right, left, reverse or alternating", name),
+        }
+    }
+}
+
+impl Effect {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "block" => Ok(Effect::Block),
+            "right" => Ok(Effect::Force(Turn::Right)),
+            "left" => Ok(Effect::Force(Turn::Left)),
+            "reverse" => Ok(Effect::Force(Turn::Reverse)),
+            "halt" => Ok(Effect::Halt),
+            _ => bail!("Unknown obstacle effect '{}', expected block, right, left, reverse or halt", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    policy: TurnPolicy,
+    effects: HashMap<char, Effect>,
+}
+
+impl Rules {
+    // The puzzle itself: '#' blocks and guards turn right.
+    pub fn puzzle() -> Self {
+        Rules { policy: TurnPolicy::Right, effects: HashMap::from([('#', Effect::Block)]) }
+    }
+
+    pub fn with_policy(mut self, policy: TurnPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_effect(mut self, obstacle: char, effect: Effect) -> Self {
+        self.effects.insert(obstacle, effect);
+        self
+    }
+
+    // every character that has an effect
+    pub fn obstacles(&self) -> Vec<char> {
+        self.effects.keys().copied().collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Status {
+    Walking,
+    Halted,
+    Exited,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Agent {
+    pub guard: Guard,
+    pub status: Status,
+    // whether the next alternating turn is a left one
+    next_left: bool,
+}
+
+impl Agent {
+    fn turn(&mut self, turn: Turn) {
+        match turn {
+            Turn::Right => self.guard.turn_right(),
+            Turn::Left => self.guard.turn_left(),
+            Turn::Reverse => self.guard.turn_around(),
+        }
+    }
+
+    fn turn_by(&mut self, policy: TurnPolicy) {
+        let turn = match (policy, self.next_left) {
+            (TurnPolicy::Right, _) | (TurnPolicy::Alternating, false) => Turn::Right,
+            (TurnPolicy::Left, _) | (TurnPolicy::Alternating, true) => Turn::Left,
+            (TurnPolicy::Reverse, _) => Turn::Reverse,
+        };
+        if policy == TurnPolicy::Alternating {
+            self.next_left = !self.next_left;
+        }
+        self.turn(turn);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    // every cell any guard stood on
+    pub visited: HashSet<(usize, usize)>,
+    pub agents: Vec<Agent>,
+    pub ticks: usize,
+    // true if the guards ended up repeating an earlier state forever
+    pub looped: bool,
+}
+
+pub fn run(data: &Grid<char>, guards: &[Guard], rules: &Rules) -> Outcome {
+    let mut agents: Vec<Agent> = guards
+        .iter()
+        .map(|&guard| Agent { guard, status: Status::Walking, next_left: false })
+        .collect();
+    let mut visited: HashSet<(usize, usize)> = guards.iter().map(|guard| (guard.x, guard.y)).collect();
+    let mut seen: HashSet<Vec<Agent>> = HashSet::new();
+    let mut ticks = 0;
+
+    while agents.iter().any(|agent| agent.status == Status::Walking) {
+        if !seen.insert(agents.clone()) {
+            return Outcome { visited, agents, ticks, looped: true };
+        }
+
+        let occupied: HashSet<(usize, usize)> = agents
+            .iter()
+            .filter(|agent| agent.status != Status::Exited)
+            .map(|agent| (agent.guard.x, agent.guard.y))
+            .collect();
+
+        for agent in agents.iter_mut().filter(|agent| agent.status == Status::Walking) {
+            let (nx, ny) = match agent.guard.ahead(data) {
+                Some(cell) => cell,
+                None => {
+                    agent.status = Status::Exited;
+                    continue;
+                },
+            };
+
+            let effect = match occupied.contains(&(nx, ny)) {
+                true => Some(Effect::Block),
+                false => rules.effects.get(&data[(nx, ny)]).copied(),
+            };
+            match effect {
+                None => {
+                    agent.guard.x = nx;
+                    agent.guard.y = ny;
+                    visited.insert((nx, ny));
+                },
+                Some(Effect::Block) => agent.turn_by(rules.policy),
+                Some(Effect::Force(turn)) => agent.turn(turn),
+                Some(Effect::Halt) => agent.status = Status::Halted,
+            }
+        }
+        ticks += 1;
+    }
+
+    Outcome { visited, agents, ticks, looped: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_grid, Direction};
+
+    fn guard(x: usize, y: usize, dir: Direction) -> Guard {
+        Guard { x, y, dir }
+    }
+
+    #[test]
+    fn test_turn_policies() {
+        let data = parse_grid(&[".#..", "...#", "....", "..#."], &['#']).unwrap();
+        let start = [guard(2, 1, Direction::Up)];
+
+        let right = run(&data, &start, &Rules::puzzle());
+        assert!(!right.looped);
+        assert_eq!(right.visited.len(), 5);
+        assert_eq!(right.agents[0].guard, guard(2, 0, Direction::Left));
+        assert_eq!(right.agents[0].status, Status::Exited);
+
+        let left = run(&data, &start, &Rules::puzzle().with_policy(TurnPolicy::Left));
+        assert_eq!(left.agents[0].guard, guard(1, 0, Direction::Left));
+        assert_eq!(left.visited.len(), 3);
+
+        let reverse = run(&data, &start, &Rules::puzzle().with_policy(TurnPolicy::Reverse));
+        assert_eq!(reverse.agents[0].guard, guard(3, 1, Direction::Down));
+
+        // bounces between the two obstacles forever
+        let corridor = parse_grid(&["#..#"], &['#']).unwrap();
+        let reverse = run(&corridor, &[guard(0, 1, Direction::Right)], &Rules::puzzle().with_policy(TurnPolicy::Reverse));
+        assert!(reverse.looped);
+
+        let alternating = run(&data, &start, &Rules::puzzle().with_policy(TurnPolicy::Alternating));
+        assert_eq!(alternating.agents[0].guard, guard(0, 2, Direction::Up));
+        assert_eq!(alternating.agents[0].status, Status::Exited);
+    }
+
+    #[test]
+    fn test_obstacle_effects() {
+        let data = parse_grid(&[".L..", "...#", "....", ".H.."], &['#', 'L', 'H']).unwrap();
+        let start = [guard(2, 1, Direction::Up)];
+        let rules = Rules::puzzle()
+            .with_effect('L', Effect::Force(Turn::Left))
+            .with_effect('H', Effect::Halt);
+
+        let outcome = run(&data, &start, &rules);
+        assert_eq!(outcome.agents[0].guard, guard(1, 0, Direction::Left));
+        assert_eq!(outcome.agents[0].status, Status::Exited);
+
+        let outcome = run(&data, &[guard(1, 1, Direction::Down)], &rules);
+        assert_eq!(outcome.agents[0].guard, guard(2, 1, Direction::Down));
+        assert_eq!(outcome.agents[0].status, Status::Halted);
+        assert_eq!(outcome.ticks, 2);
+    }
+
+    #[test]
+    fn test_several_guards() {
+        // two guards walking towards each other in the same row
+        let data = parse_grid(&["....."], &['#']).unwrap();
+        let outcome = run(&data, &[guard(0, 0, Direction::Right), guard(0, 4, Direction::Left)], &Rules::puzzle());
+        assert!(!outcome.looped);
+        assert_eq!(outcome.visited.len(), 5);
+        assert!(outcome.agents.iter().all(|agent| agent.status == Status::Exited));
+
+        // and two that keep each other trapped
+        let data = parse_grid(&["#..#"], &['#']).unwrap();
+        let outcome = run(&data, &[guard(0, 1, Direction::Right), guard(0, 2, Direction::Left)],
+                          &Rules::puzzle().with_policy(TurnPolicy::Reverse));
+        assert!(outcome.looped);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{Direction, Guard};
+use grid::Grid;
+use std::thread;
+use std::time::Duration;
+
+// Replays the patrol one step at a time. Every move or turn is a frame, drawn
+// the way the puzzle text draws it: either an X on every visited cell, or |, -
+// and + for the direction the guard passed through. A part two obstacle shows
+// up as an O and, once the guard is stuck, the cells of the loop are picked out
+// in color.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Trail {
+    Marks,
+    Lines,
+}
+
+const VERTICAL: u8 = 1;
+const HORIZONTAL: u8 = 2;
+
+const ANSI_LOOP: &str = "\x1b[1;33m";
+const ANSI_OBSTACLE: &str = "\x1b[1;31m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+fn orientation(dir: Direction) -> u8 {
+    match dir {
+        Direction::Up | Direction::Down => VERTICAL,
+        Direction::Left | Direction::Right => HORIZONTAL,
+    }
+}
+
+fn arrow(dir: Direction) -> char {
+    match dir {
+        Direction::Up => '^',
+        Direction::Down => 'v',
+        Direction::Left => '<',
+        Direction::Right => '>',
+    }
+}
+
+pub struct Replay {
+    data: Grid<char>,
+    extra: Option<(usize, usize)>,
+    // the guard after every move or turn, starting with where it stands
+    steps: Vec<Guard>,
+    // when the guard is stuck, the step its final state was first seen at
+    cycle_start: Option<usize>,
+}
+
+impl Replay {
+    pub fn record(data: &Grid<char>, guard: &Guard, extra: Option<(usize, usize)>) -> Self {
+        // a stuck guard is recorded until it is back where its loop started
+        let (_, cycle) = crate::simulate(data, guard, extra, true);
+        let last = cycle.as_ref().map(|cycle| cycle.entry + cycle.length);
+
+        let mut guard = *guard;
+        let mut steps = vec![guard];
+        while last.is_none_or(|last| steps.len() <= last) && crate::step(data, extra, &mut guard).is_some() {
+            steps.push(guard);
+        }
+
+        Replay { data: data.clone(), extra, steps, cycle_start: cycle.map(|cycle| cycle.entry) }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.steps.len()
+    }
+
+    // Number of moves and turns in the loop, if the guard got stuck in one.
+    pub fn loop_length(&self) -> Option<usize> {
+        self.cycle_start.map(|start| self.steps.len() - 1 - start)
+    }
+
+    pub fn status(&self, frame: usize) -> String {
+        let last = frame + 1 == self.frames();
+        match (last, self.loop_length()) {
+            (true, Some(length)) => format!("step {}/{}, stuck in a loop of {} steps", frame, self.frames() - 1, length),
+            (true, None) => format!("step {}/{}, the guard left the map", frame, self.frames() - 1),
+            (false, _) => format!("step {}/{}", frame, self.frames() - 1),
+        }
+    }
+
+    pub fn render(&self, frame: usize, trail: Trail, ansi: bool) -> String {
+        let frame = frame.min(self.frames() - 1);
+        let guard = self.steps[frame];
+
+        let mut trails = vec![0u8; self.data.rows() * self.data.cols()];
+        for step in &self.steps[..=frame] {
+            trails[step.x * self.data.cols() + step.y] |= orientation(step.dir);
+        }
+
+        let mut on_loop = vec![false; trails.len()];
+        if let (Some(start), true) = (self.cycle_start, frame + 1 == self.frames()) {
+            for step in &self.steps[start..] {
+                on_loop[step.x * self.data.cols() + step.y] = true;
+            }
+        }
+
+        let mut output = String::new();
+        for x in 0..self.data.rows() {
+            for y in 0..self.data.cols() {
+                let index = x * self.data.cols() + y;
+                let c = if (x, y) == (guard.x, guard.y) {
+                    arrow(guard.dir)
+                } else if self.extra == Some((x, y)) {
+                    'O'
+                } else {
+                    match (trail, trails[index]) {
+                        (_, 0) => self.data[(x, y)],
+                        (Trail::Marks, _) => 'X',
+                        (Trail::Lines, VERTICAL) => '|',
+                        (Trail::Lines, HORIZONTAL) => '-',
+                        (Trail::Lines, _) => '+',
+                    }
+                };
+
+                let color = match (ansi, self.extra == Some((x, y)), on_loop[index]) {
+                    (false, _, _) => None,
+                    (true, true, _) => Some(ANSI_OBSTACLE),
+                    (true, false, true) => Some(ANSI_LOOP),
+                    (true, false, false) => None,
+                };
+                match color {
+                    Some(color) => output.push_str(&format!("{}{}{}", color, c, ANSI_RESET)),
+                    None => output.push(c),
+                }
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    // Frames to show when only every `stride`th step is wanted. The last frame
+    // is always included so the outcome is never skipped.
+    fn shown(&self, stride: usize) -> Vec<usize> {
+        let mut frames: Vec<usize> = (0..self.frames()).step_by(stride.max(1)).collect();
+        if frames.last() != Some(&(self.frames() - 1)) {
+            frames.push(self.frames() - 1);
+        }
+
+        frames
+    }
+
+    pub fn play(&self, trail: Trail, delay: Duration, stride: usize) {
+        for frame in self.shown(stride) {
+            println!("\x1b[2J\x1b[H{}{}", self.render(frame, trail, true), self.status(frame));
+            thread::sleep(delay);
+        }
+    }
+
+    // asciicast v2: a JSON header line followed by one [time, "o", text] event per frame.
+    pub fn to_asciicast(&self, trail: Trail, delay: Duration, stride: usize) -> String {
+        let mut output = format!(
+            "{{\"version\": 2, \"width\": {}, \"height\": {}}}\n",
+            self.data.cols().max(60),
+            self.data.rows() + 1,
+        );
+
+        for (i, frame) in self.shown(stride).into_iter().enumerate() {
+            let text = format!("\x1b[2J\x1b[H{}{}", self.render(frame, trail, true), self.status(frame))
+                .replace('\n', "\r\n");
+            let time = delay.as_secs_f64() * i as f64;
+            output.push_str(&format!("[{:.3}, \"o\", {}]\n", time, json_string(&text)));
+        }
+
+        output
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut output = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grid;
+
+    fn start() -> (Grid<char>, Guard) {
+        let data = parse_grid(&[".#..", "...#", "....", "..#."], &['#']).unwrap();
+        (data, Guard { x: 2, y: 1, dir: Direction::Up })
+    }
+
+    #[test]
+    fn test_replay_lines() {
+        let (data, guard) = start();
+        let replay = Replay::record(&data, &guard, None);
+        assert_eq!(replay.loop_length(), None);
+        assert_eq!(replay.render(0, Trail::Lines, false), ".#..\n...#\n.^..\n..#.\n");
+        let last = replay.frames() - 1;
+        assert_eq!(replay.render(last, Trail::Lines, false), ".#..\n.++#\n<++.\n..#.\n");
+        assert_eq!(replay.render(last, Trail::Marks, false), ".#..\n.XX#\n<XX.\n..#.\n");
+        assert!(replay.status(last).ends_with("the guard left the map"));
+    }
+
+    #[test]
+    fn test_replay_loop() {
+        let (data, guard) = start();
+        let replay = Replay::record(&data, &guard, Some((2, 0)));
+        assert_eq!(replay.loop_length(), Some(8));
+        let last = replay.frames() - 1;
+        assert_eq!(replay.render(last, Trail::Lines, false), ".#..\n.++#\nO^+.\n..#.\n");
+        let colored = replay.render(last, Trail::Lines, true);
+        assert!(colored.contains("\x1b[1;31mO\x1b[0m"));
+        assert!(colored.contains("\x1b[1;33m+\x1b[0m"));
+        assert!(replay.status(last).ends_with("stuck in a loop of 8 steps"));
+    }
+
+    #[test]
+    fn test_asciicast() {
+        let (data, guard) = start();
+        let replay = Replay::record(&data, &guard, None);
+        let cast = replay.to_asciicast(Trail::Lines, Duration::from_millis(100), 1);
+        let lines: Vec<&str> = cast.lines().collect();
+        assert_eq!(lines[0], "{\"version\": 2, \"width\": 60, \"height\": 5}");
+        assert_eq!(lines.len(), replay.frames() + 1);
+        assert!(lines[1].starts_with("[0.000, \"o\", \"\\u001b[2J\\u001b[H.#..\\r\\n"));
+        assert!(lines[2].starts_with("[0.100, "));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::Guard;
+use crate::jump::dir_index;
+
+// The set of guard states already seen, as one bit per cell and direction.
+// Loop checks for different obstacles reuse the same set, so clearing it has to
+// be cheap: only the words that were written to since the last reset are zeroed
+// again, and nothing is allocated once the set has warmed up.
+
+pub struct StateSet {
+    cols: usize,
+    bits: Vec<u64>,
+    // indices into `bits` that may be non-zero
+    touched: Vec<usize>,
+}
+
+impl StateSet {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        StateSet { cols, bits: vec![0; (rows * cols * 4).div_ceil(64)], touched: Vec::new() }
+    }
+
+    // true if the state was not in the set yet
+    pub fn insert(&mut self, guard: &Guard) -> bool {
+        let index = (guard.x * self.cols + guard.y) * 4 + dir_index(guard.dir);
+        let (word, bit) = (index / 64, 1 << (index % 64));
+
+        if self.bits[word] & bit != 0 {
+            return false;
+        }
+        if self.bits[word] == 0 {
+            self.touched.push(word);
+        }
+        self.bits[word] |= bit;
+
+        true
+    }
+
+    pub fn reset(&mut self) {
+        for word in self.touched.drain(..) {
+            self.bits[word] = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Direction;
+
+    #[test]
+    fn test_state_set() {
+        let mut states = StateSet::new(20, 20);
+        let guard = Guard { x: 3, y: 7, dir: Direction::Left };
+        assert!(states.insert(&guard));
+        assert!(!states.insert(&guard));
+        assert!(states.insert(&Guard { dir: Direction::Up, ..guard }));
+        assert!(states.insert(&Guard { x: 19, y: 19, dir: Direction::Left }));
+        assert_eq!(states.touched.len(), 2);
+
+        states.reset();
+        assert!(states.bits.iter().all(|&word| word == 0));
+        assert!(states.touched.is_empty());
+        assert!(states.insert(&guard));
+    }
+}
@@ -1,82 +1,96 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use num_bigint::BigInt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+mod operators;
+mod solver;
+
+use operators::{Number, Operator, Operators};
 
 // data cleaning functions
 
-fn parse_data(filepath: &str) -> Result<Vec<(i64, Vec<i64>)>> {
+fn parse_data<N: Number>(filepath: &str) -> Result<Vec<(N, Vec<N>)>> {
     let file = File::open(filepath)?;
-    let data: Vec<(i64,Vec<i64>)> = BufReader::new(file)
+    let data: Vec<(N,Vec<N>)> = BufReader::new(file)
         .lines()
-        .map(|line| {
-            let line = line.with_context(|| "Could not read line")?;
-            let (total, parts) = line
-                .split_once(":")
-                .with_context(|| "Could not split line: {}, line")?;
-            let total = total
-                .parse::<i64>()
-                .with_context(|| format!("Could not parse the total: {} to an integer", total))?;
-            let parts = parts
-                .split_whitespace()
-                .map(|x| x.parse::<i64>()
-                    .with_context(|| format!("Could not parse the part: '{}' to an integer", x)))
-                .collect::<Result<Vec<i64>>>()?;
-            Ok((total, parts))
-        })
-        .collect::<Result<Vec<(i64, Vec<i64>)>>>()?;
+        .map(|line| parse_line(&line.with_context(|| "Could not read line")?))
+        .collect::<Result<Vec<(N, Vec<N>)>>>()?;
 
     Ok(data)
 }
 
+fn parse_line<N: Number>(line: &str) -> Result<(N, Vec<N>)> {
+    let (total, parts) = line
+        .split_once(":")
+        .with_context(|| format!("Could not split line: {}", line))?;
+    let total = N::parse(total)
+        .with_context(|| format!("Could not parse the total: {} to an integer", total))?;
+    let parts = parts
+        .split_whitespace()
+        .map(|x| N::parse(x)
+            .with_context(|| format!("Could not parse the part: '{}' to an integer", x)))
+        .collect::<Result<Vec<N>>>()?;
+    Ok((total, parts))
+}
+
 // Part 1 Functions
 
-fn evaluate(nums: &Vec<i64>, operators: &Vec<char>) -> Result<i64> {
-    let mut result = 0;
+fn add_total<N: Number>(result: &N, total: &N) -> Result<N> {
+    result
+        .checked_add(total)
+        .with_context(|| format!("The calibration result overflowed after {}, try --i128 or --bigint", result))
+}
+
+// Stops at the first step that overflows rather than wrapping around.
+fn evaluate<N: Number>(nums: &[N], operators: &[&dyn Operator<N>]) -> Result<N> {
+    let mut result = N::zero();
     match nums.len() {
         0 => Ok(result),
-        1 => Ok(nums[0]),
+        1 => Ok(nums[0].clone()),
         _ => {
-            result = nums[0];
-            for (i, num) in nums.iter().skip(1).enumerate() {
-                match operators[i] {
-                    '+' => result += num,
-                    '*' => result *= num,
-                    '|' => result = concatenate(&result, num), 
-                    _ => (),
-                }
+            result = nums[0].clone();
+            for (op, num) in operators.iter().zip(nums.iter().skip(1)) {
+                result = match op.apply(&result, num) {
+                    Some(value) => value,
+                    None => bail!("Could not evaluate {} {} {}", result, op.symbol(), num),
+                };
             }
             Ok(result)
         }
     }
 }
 
-fn find_combo(total: &i64, nums: &Vec<i64>) -> Result<bool> {
-    let mut operators = vec!['+'; nums.len() - 1];
+// Tries every assignment of the given operators. Superseded by
+// solver::solvable and kept so the benchmark has something to compare against.
+fn find_combo<N: Number>(total: &N, nums: &[N], ops: &Operators<N>) -> Result<bool> {
+    if nums.is_empty() {
+        return Ok(false)
+    }
+    let choices: Vec<&dyn Operator<N>> = ops.iter().collect();
+    let mut operators = vec![choices[0]; nums.len() - 1];
     
-    for i in 0..2i64.pow(operators.len() as u32) {
+    for i in 0..choices.len().pow(operators.len() as u32) {
         let mut temp = i;
-        for j in 0..operators.len() {
-            operators[j] = match temp % 2 {
-                0 => '+',
-                1 => '*',
-                _ => '+',
-            };
-            temp /= 2;
+        for operator in operators.iter_mut() {
+            *operator = choices[temp % choices.len()];
+            temp /= choices.len();
         }
-        if evaluate(nums, &operators)? == *total {
+        // an assignment that can't be evaluated just doesn't make the equation true
+        if evaluate(nums, &operators).ok().as_ref() == Some(total) {
             return Ok(true)
         }
     }
     Ok(false)
 }
 
-fn part_one(filepath: &str) -> Result<i64> {
-    let data = parse_data(filepath)?;
-    let mut result = 0;
+fn part_one<N: Number>(filepath: &str) -> Result<N> {
+    let data = parse_data::<N>(filepath)?;
+    let mut result = N::zero();
     for (total, nums) in data {
-        if find_combo(&total, &nums)? {
-            result += total;
+        if solver::solvable(&total, &nums, &Operators::part_one()) {
+            result = add_total(&result, &total)?;
         }
     }
     Ok(result)
@@ -84,54 +98,222 @@ fn part_one(filepath: &str) -> Result<i64> {
 
 // Part 2 Functions
 
-fn concatenate(i: &i64, j: &i64) -> i64 {
-    i * 10i64.pow(j.ilog10() + 1) + j
+// Only defined for non-negative numbers. Zero has one digit, so i || 0 is i * 10.
+fn concatenate<N: Number>(i: &N, j: &N) -> Option<N> {
+    if i < &N::zero() {
+        return None;
+    }
+    match j.shift() {
+        Some(shift) => i.checked_mul(&shift)?.checked_add(j),
+        // j has as many digits as N can hold, so only 0 || j fits
+        None if i.is_zero() && j >= &N::zero() => Some(j.clone()),
+        None => None,
+    }
 }
 
-fn find_combo_two(total: &i64, nums: &Vec<i64>) -> Result<bool> {
-    let mut operators = vec!['+'; nums.len() - 1];
-    
-    for i in 0..3i64.pow(operators.len() as u32) {
-        let mut temp = i;
-        for j in 0..operators.len() {
-            operators[j] = match temp % 3 {
-                0 => '+',
-                1 => '*',
-                2 => '|',
-                _ => '+',
-            };
-            temp /= 3;
-        }
-        if evaluate(nums, &operators)? == *total {
-            return Ok(true)
+fn part_two<N: Number>(filepath: &str) -> Result<N> {
+    let data = parse_data::<N>(filepath)?;
+    let mut result = N::zero();
+    for (total, nums) in data {
+        if solver::solvable(&total, &nums, &Operators::part_two()) {
+            result = add_total(&result, &total)?;
         }
     }
-    Ok(false)
+    Ok(result)
 }
 
-fn part_two(filepath: &str) -> Result<i64> {
-    let data = parse_data(filepath)?;
-    let mut result = 0;
+// verification report
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Listing {
+    // one witness per equation
+    First,
+    // every solution
+    All,
+    // only how many solutions there are
+    Count,
+}
+
+fn check_witness<N: Number>(total: &N, nums: &[N], ops: &[&dyn Operator<N>]) -> Result<String> {
+    let expression = solver::expression(nums, ops);
+    if &evaluate(nums, ops)? != total {
+        bail!("The witness {} does not evaluate to {}", expression, total);
+    }
+
+    Ok(expression)
+}
+
+// Lists how each equation can be made true, re-evaluating every witness
+// left to right to check it really gives the test value.
+fn verify<N: Number>(data: &[(N, Vec<N>)], ops: &Operators<N>, listing: Listing) -> Result<String> {
+    let mut output = String::new();
+    let mut calibrated = 0;
+    let mut sum = N::zero();
+
     for (total, nums) in data {
-        if find_combo(&total, &nums)? {
-            result += total;
+        let solutions = match listing {
+            Listing::First => solver::solve(total, nums, ops).into_iter().collect(),
+            Listing::All => solver::solutions(total, nums, ops),
+            Listing::Count => Vec::new(),
+        };
+        let count = match listing {
+            Listing::First => solutions.len(),
+            Listing::All => solutions.len(),
+            Listing::Count => solver::count(total, nums, ops),
+        };
+
+        if count == 0 {
+            let nums: Vec<String> = nums.iter().map(|num| num.to_string()).collect();
+            output.push_str(&format!("{}: {} has no solution\n", total, nums.join(" ")));
             continue;
         }
-        if find_combo_two(&total, &nums)? {
-            result += total;
+        calibrated += 1;
+        sum = add_total(&sum, total)?;
+
+        let counted = format!("{}: {} solution{}\n", total, count, if count == 1 { "" } else { "s" });
+        match listing {
+            Listing::First => output.push_str(&format!("{}: {}\n", total, check_witness(total, nums, &solutions[0])?)),
+            Listing::Count => output.push_str(&counted),
+            Listing::All => {
+                output.push_str(&counted);
+                for ops in &solutions {
+                    output.push_str(&format!("    {}\n", check_witness(total, nums, ops)?));
+                }
+            },
         }
     }
-    Ok(result)
+
+    output.push_str(&format!("{} of {} equations can be made true, total calibration result {}\n", calibrated, data.len(), sum));
+    if listing != Listing::Count {
+        output.push_str("Every witness was checked by evaluating it left to right\n");
+    }
+
+    Ok(output)
+}
+
+// benchmark functions
+
+struct Lcg(u64);
+
+impl Lcg {
+    fn below(&mut self, n: u64) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n
+    }
+}
+
+// Equations of `length` small numbers. About half are built from random
+// operators so they have a solution, the rest get a nearby total that usually
+// has none, which is the worst case for the exhaustive search.
+fn synthetic_data(equations: usize, length: usize, seed: u64) -> Vec<(i64, Vec<i64>)> {
+    let mut rng = Lcg(seed);
+    let choices: Vec<&dyn Operator<i64>> = Operators::part_two().iter().collect();
+    (0..equations)
+        .map(|i| {
+            let nums: Vec<i64> = (0..length).map(|_| 1 + rng.below(9) as i64).collect();
+            let operators: Vec<&dyn Operator<i64>> = (1..length).map(|_| choices[rng.below(3) as usize]).collect();
+            let total = evaluate(&nums, &operators).expect("operators are valid");
+            (total + (i % 2) as i64, nums)
+        })
+        .collect()
+}
+
+fn time_sum(data: &[(i64, Vec<i64>)], check: impl Fn(&i64, &[i64]) -> Result<bool>) -> Result<(i64, Duration)> {
+    let now = Instant::now();
+    let mut sum = 0;
+    for (total, nums) in data {
+        if check(total, nums)? {
+            sum += total;
+        }
+    }
+
+    Ok((sum, now.elapsed()))
+}
+
+fn benchmark(equations: usize, length: usize) -> Result<String> {
+    let data = synthetic_data(equations, length, 2024);
+    let mut output = format!("{} equations of {} numbers\n", equations, length);
+
+    for (name, ops) in [("+ and *", Operators::part_one()), ("+, * and ||", Operators::part_two())] {
+        let (expected, exhaustive_time) = time_sum(&data, |total, nums| find_combo(total, nums, &ops))?;
+        let (sum, solver_time) = time_sum(&data, |total, nums| Ok(solver::solvable(total, nums, &ops)))?;
+        if sum != expected {
+            bail!("The backward solver disagrees with the exhaustive search: {} vs {}", sum, expected);
+        }
+
+        output.push_str(&format!("{}\n", name));
+        output.push_str(&format!("    Every operator assignment: {:.2?}\n", exhaustive_time));
+        output.push_str(&format!("    Backward solver:           {:.2?}\n", solver_time));
+    }
+
+    Ok(output)
+}
+
+// Reads the equations into N and prints the report asked for on the command line.
+fn show_verify<N: Number>(filepath: &str, args: &[String]) {
+    let ops = match args.iter().position(|arg| arg == "--ops") {
+        Some(i) => match args.get(i + 1).map(|list| Operators::parse(list)) {
+            Some(Ok(ops)) => ops,
+            Some(Err(e)) => return println!("Error: Could not read the operators. {}", e),
+            None => return println!("Error: --ops needs a list of operators, like --ops \"+,*,-\""),
+        },
+        None if args.iter().any(|arg| arg == "--part-one") => Operators::part_one(),
+        None => Operators::part_two(),
+    };
+    let listing = if args.iter().any(|arg| arg == "--all") {
+        Listing::All
+    } else if args.iter().any(|arg| arg == "--count") {
+        Listing::Count
+    } else {
+        Listing::First
+    };
+
+    match parse_data::<N>(filepath).and_then(|data| verify(&data, &ops, listing)) {
+        Ok(output) => print!("{}", output),
+        Err(e) => println!("Error: Could not verify the equations. {}", e),
+    };
 }
 
 fn main() {
     let filepath = "input.txt";
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("verify") {
+        if args.iter().any(|arg| arg == "--bigint") {
+            show_verify::<BigInt>(filepath, &args);
+        } else if args.iter().any(|arg| arg == "--i128") {
+            show_verify::<i128>(filepath, &args);
+        } else {
+            show_verify::<i64>(filepath, &args);
+        }
+        return;
+    }
 
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let equations = args.get(2).and_then(|n| n.parse::<usize>().ok()).unwrap_or(100);
+        let length = args.get(3).and_then(|n| n.parse::<usize>().ok()).unwrap_or(12);
+        match benchmark(equations, length) {
+            Ok(output) => print!("{}", output),
+            Err(e) => println!("Error: Could not run the benchmark. {}", e),
+        };
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--bigint") {
+        solve::<BigInt>(filepath);
+    } else if args.iter().any(|arg| arg == "--i128") {
+        solve::<i128>(filepath);
+    } else {
+        solve::<i64>(filepath);
+    }
+}
+
+fn solve<N: Number>(filepath: &str) {
     println!("Hello, Advent of Code 2024!");
     
     let now = Instant::now();
 
-    match part_one(filepath) {
+    match part_one::<N>(filepath) {
         Ok(answer) => println!("Day 7, Part 1 answer: {}", answer),
         Err(e) => println!("Error: Could not calculate part one answer. {}", e),
     };
@@ -139,7 +321,7 @@ fn main() {
     println!("Day 7, Part 1 time elapsed {:.2?}", now.elapsed());
     let now = Instant::now();
 
-    match part_two(filepath) {
+    match part_two::<N>(filepath) {
         Ok(answer) => println!("Day 7, Part 2 answer: {}", answer),
         Err(e) => println!("Error: Could not calculate part two answer. {}", e),
     };
@@ -151,6 +333,10 @@ fn main() {
 mod tests {
     use super::*;
 
+    fn ops(symbols: &[&str]) -> Vec<&'static dyn Operator<i64>> {
+        symbols.iter().map(|symbol| operators::registry().into_iter().find(|op| op.symbol() == *symbol).unwrap()).collect()
+    }
+
     #[test]
     fn test_main() {
         main();
@@ -159,54 +345,171 @@ mod tests {
     #[test]
     fn test_evaluate_add() {
         let nums = vec![1, 2, 3, 4, 5];
-        let operators = vec!['+', '+', '+', '+'];
+        let operators = ops(&["+", "+", "+", "+"]);
         assert_eq!(evaluate(&nums, &operators).unwrap(), 15);
     }
 
     #[test]
     fn test_evaluate_mul() {
         let nums = vec![1, 2, 3, 4, 5];
-        let operators = vec!['*', '*', '*', '*'];
+        let operators = ops(&["*", "*", "*", "*"]);
         assert_eq!(evaluate(&nums, &operators).unwrap(), 120);
     }
 
     #[test]
     fn test_concatenate() {
-        assert_eq!(concatenate(&1, &2), 12);
-        assert_eq!(concatenate(&12, &3), 123);
-        assert_eq!(concatenate(&123, &4), 1234);
+        assert_eq!(concatenate(&1i64, &2), Some(12));
+        assert_eq!(concatenate(&12i64, &3), Some(123));
+        assert_eq!(concatenate(&123i64, &4), Some(1234));
+        assert_eq!(concatenate(&5i64, &0), Some(50));
+        assert_eq!(concatenate(&0i64, &0), Some(0));
+        assert_eq!(concatenate(&0i64, &i64::MAX), Some(i64::MAX));
+        assert_eq!(concatenate(&1i64, &i64::MAX), None);
+        assert_eq!(concatenate(&i64::MAX, &1), None);
+        assert_eq!(concatenate(&-1i64, &5), None);
+        assert_eq!(concatenate(&1i128, &(i64::MAX as i128)), Some(10i128.pow(19) + i64::MAX as i128));
+    }
+
+    #[test]
+    fn test_evaluate_overflow() {
+        let nums = vec![i64::MAX, 1, 0];
+        assert!(evaluate(&nums, &ops(&["+", "*"])).is_err());
+        assert!(evaluate(&nums, &ops(&["||", "+"])).is_err());
+        assert_eq!(evaluate(&nums, &ops(&["*", "||"])).ok(), None);
+        assert_eq!(evaluate(&nums, &ops(&["-", "*"])).unwrap(), 0);
+        assert!(!find_combo(&0, &[i64::MAX, 2, 0], &Operators::part_one()).unwrap());
+        assert!(find_combo(&70i64, &[7, 0], &Operators::part_two()).unwrap());
+        assert!(!find_combo(&0i64, &[], &Operators::part_two()).unwrap());
     }
 
     #[test]
     fn test_evaluate_concat() {
         let nums = vec![1, 2, 3, 4, 5];
-        let operators = vec!['|', '|', '|', '|'];
+        let operators = ops(&["||", "||", "||", "||"]);
         assert_eq!(evaluate(&nums, &operators).unwrap(), 12345);
     }
 
     #[test]
     fn test_evaluate_add_mul() {
         let nums = vec![1, 2, 3, 4, 5];
-        let operators = vec!['+', '*', '+', '*'];
+        let operators = ops(&["+", "*", "+", "*"]);
         assert_eq!(evaluate(&nums, &operators).unwrap(), 65);
     }
 
     #[test]
     fn test_evaluate_add_mul_concat() {
         let nums = vec![1, 2, 3, 4, 5];
-        let operators = vec!['+', '*', '|', '*'];
+        let operators = ops(&["+", "*", "||", "*"]);
         assert_eq!(evaluate(&nums, &operators).unwrap(), 470);
     }
 
+    const EXAMPLE: [&str; 9] = [
+        "190: 10 19",
+        "3267: 81 40 27",
+        "83: 17 5",
+        "156: 15 6",
+        "7290: 6 8 6 15",
+        "161011: 16 10 13",
+        "192: 17 8 14",
+        "21037: 9 7 18 13",
+        "292: 11 6 16 20",
+    ];
+
+    #[test]
+    fn test_example_answers() {
+        let data: Vec<(i64, Vec<i64>)> = EXAMPLE.iter().map(|line| parse_line(line).unwrap()).collect();
+        let sum = |ops: &Operators<i64>| data
+            .iter()
+            .filter(|(total, nums)| solver::solvable(total, nums, ops))
+            .map(|(total, _)| total)
+            .sum::<i64>();
+        assert_eq!(sum(&Operators::part_one()), 3749);
+        assert_eq!(sum(&Operators::part_two()), 11387);
+
+        for list in ["+,*", "+,*,||", "+,-", "*,/,^"] {
+            let ops = Operators::parse(list).unwrap();
+            for (total, nums) in &data {
+                assert_eq!(solver::solvable(total, nums, &ops), find_combo(total, nums, &ops).unwrap(), "{} {}", list, total);
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let data: Vec<(i64, Vec<i64>)> = EXAMPLE.iter().map(|line| parse_line(line).unwrap()).collect();
+
+        let report = verify(&data, &Operators::part_one(), Listing::First).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "190: 10 * 19");
+        assert_eq!(lines[1], "3267: 81 * 40 + 27");
+        assert_eq!(lines[2], "83: 17 5 has no solution");
+        assert_eq!(lines[9], "3 of 9 equations can be made true, total calibration result 3749");
+
+        let report = verify(&data, &Operators::part_two(), Listing::All).unwrap();
+        assert!(report.contains("3267: 2 solutions\n    81 * 40 + 27\n    81 + 40 * 27\n"));
+        assert!(report.contains("7290: 1 solution\n    6 * 8 || 6 * 15\n"));
+        assert!(report.contains("6 of 9 equations can be made true, total calibration result 11387"));
+
+        let report = verify(&data, &Operators::part_two(), Listing::Count).unwrap();
+        assert!(report.contains("292: 1 solution\n"));
+        assert!(!report.contains("witness"));
+
+        let signed = [parse_line::<i64>("2: 5 9 6").unwrap()];
+        let report = verify(&signed, &Operators::parse("+,-").unwrap(), Listing::First).unwrap();
+        assert!(report.starts_with("2: 5 - 9 + 6\n"));
+
+        assert!(check_witness(&3266, &[81, 40, 27], &ops(&["+", "*"])).is_err());
+    }
+
+    #[test]
+    fn test_solver_matches_exhaustive() {
+        let max = i64::MAX;
+        let equations = [(0, vec![max, 2, 0]), (1, vec![max, 2, 0]), (0, vec![max, 1, 0]), (max, vec![max, 1, 1]),
+                         (0, vec![0, 0, 0]), (0, vec![5, 0, 3, 0]), (50, vec![5, 0]), (1, vec![max, max, 0]),
+                         (-2, vec![4, -2]), (3, vec![-7, -2, 1]), (-4, vec![9, -2, -1])];
+        for list in ["+,*", "+,*,||", "+,*,^", "+,-,*", "*,/,^"] {
+            let ops = Operators::parse(list).unwrap();
+            for (total, nums) in &equations {
+                assert_eq!(solver::solvable(total, nums, &ops), find_combo(total, nums, &ops).unwrap(), "{} {} {:?}", list, total, nums);
+            }
+        }
+
+        let data = [(0, vec![max, 2, 0]), (1, vec![max, 2, 0])];
+        let report = verify(&data, &Operators::parse("+,*,^").unwrap(), Listing::First).unwrap();
+        assert!(report.contains("0 of 2 equations can be made true"));
+    }
+
+    #[test]
+    fn test_wide_numbers() {
+        let line = "100000000000000000000: 10000000000 10000000000";
+        assert!(parse_line::<i64>(line).is_err());
+        let wide = [parse_line::<i128>(line).unwrap()];
+        let report = verify(&wide, &Operators::part_one(), Listing::First).unwrap();
+        assert!(report.starts_with("100000000000000000000: 10000000000 * 10000000000\n"));
+
+        // fits in i64 on its own, but not once it is added to the running total
+        let data = [parse_line::<i64>(&format!("{}: {}", i64::MAX, i64::MAX)).unwrap(), parse_line("1: 1").unwrap()];
+        assert!(verify(&data, &Operators::part_one(), Listing::Count).is_err());
+        let data = [parse_line::<BigInt>(&format!("{}: {}", i64::MAX, i64::MAX)).unwrap(), parse_line("1: 1").unwrap()];
+        let report = verify(&data, &Operators::part_one(), Listing::Count).unwrap();
+        assert!(report.ends_with("total calibration result 9223372036854775808\n"));
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let output = benchmark(20, 6).unwrap();
+        assert!(output.starts_with("20 equations of 6 numbers"));
+    }
+
     #[test]
     fn test_part_one() {
         let filepath = "part_one_test_input.txt";
-        assert_eq!(part_one(filepath).unwrap(), 3749);
+        assert_eq!(part_one::<i64>(filepath).unwrap(), 3749);
     }
 
     #[test]
     fn test_part_two() {
         let filepath = "part_one_test_input.txt";
-        assert_eq!(part_two(filepath).unwrap(), 11387);
+        assert_eq!(part_two::<i64>(filepath).unwrap(), 11387);
     }
 }
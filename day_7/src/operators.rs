@@ -0,0 +1,391 @@
+use anyhow::{bail, Context, Result};
+use num_bigint::BigInt;
+use num_integer::Roots;
+use num_traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, ToPrimitive, Zero};
+use std::fmt::{Debug, Display};
+
+// The operators an equation can use. Each one knows how to combine two numbers
+// left to right, and how to undo that for the backward solver: given a result
+// and the right operand, which left operands could have produced it.
+//
+// All arithmetic is checked. A result that does not fit is treated as no result
+// at all, so the forward evaluation stops there and the backward solver drops
+// the branch. Equations are read into one Number type, i64 by default or
+// widened to i128 or a BigInt when the test values themselves don't fit.
+
+pub trait Number: Clone + Debug + Display + Ord + From<i64> + Zero + One + CheckedAdd + CheckedSub
+    + CheckedMul + CheckedDiv + ToPrimitive + Roots + Send + Sync + 'static {
+    fn parse(token: &str) -> Result<Self>;
+
+    // 10^(number of digits in `self`), so that i || n == i * n.shift() + n.
+    // None for negative numbers, or when the shift does not fit.
+    fn shift(&self) -> Option<Self>;
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        $(impl Number for $t {
+            fn parse(token: &str) -> Result<Self> {
+                token
+                    .parse::<$t>()
+                    .with_context(|| format!("Could not parse '{}' to an integer", token))
+            }
+
+            fn shift(&self) -> Option<Self> {
+                match *self {
+                    0 => Some(10),
+                    n if n < 0 => None,
+                    n => (10 as $t).checked_pow(n.ilog10() + 1),
+                }
+            }
+        })*
+    };
+}
+
+impl_number!(i64, i128);
+
+impl Number for BigInt {
+    fn parse(token: &str) -> Result<Self> {
+        token
+            .parse::<BigInt>()
+            .with_context(|| format!("Could not parse '{}' to an integer", token))
+    }
+
+    fn shift(&self) -> Option<Self> {
+        match self < &BigInt::zero() {
+            true => None,
+            false => Some(num_traits::pow(BigInt::from(10), self.to_string().len())),
+        }
+    }
+}
+
+pub enum Inverse<N> {
+    // every left operand that gives the result, possibly none
+    Values(Vec<N>),
+    // every left operand from the first to the second inclusive, for windows
+    // too wide to list
+    Range(N, N),
+    // any left operand at all gives the result, like anything * 0 == 0
+    Any,
+}
+
+pub trait Operator<N: Number>: Sync {
+    fn symbol(&self) -> &'static str;
+
+    // None if the result is undefined or does not fit
+    fn apply(&self, left: &N, right: &N) -> Option<N>;
+
+    fn inverse(&self, result: &N, right: &N) -> Inverse<N>;
+
+    // true if non-negative operands always give a non-negative result, which
+    // lets the solver drop any branch that needs a negative value
+    fn keeps_non_negative(&self) -> bool {
+        true
+    }
+
+    // true if, for non-negative operands, a larger left operand never gives a
+    // smaller result, which lets the solver bound every way of evaluating a
+    // list of numbers by the largest one
+    fn grows_with_left(&self) -> bool {
+        true
+    }
+}
+
+pub struct Add;
+pub struct Multiply;
+pub struct Concatenate;
+pub struct Subtract;
+pub struct Divide;
+pub struct Power;
+
+impl<N: Number> Operator<N> for Add {
+    fn symbol(&self) -> &'static str {
+        "+"
+    }
+
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_add(right)
+    }
+
+    fn inverse(&self, result: &N, right: &N) -> Inverse<N> {
+        Inverse::Values(result.checked_sub(right).into_iter().collect())
+    }
+}
+
+impl<N: Number> Operator<N> for Multiply {
+    fn symbol(&self) -> &'static str {
+        "*"
+    }
+
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_mul(right)
+    }
+
+    fn inverse(&self, result: &N, right: &N) -> Inverse<N> {
+        if right.is_zero() {
+            return match result.is_zero() {
+                true => Inverse::Any,
+                false => Inverse::Values(Vec::new()),
+            };
+        }
+
+        let left = result.checked_div(right).filter(|left| left.checked_mul(right).as_ref() == Some(result));
+        Inverse::Values(left.into_iter().collect())
+    }
+}
+
+impl<N: Number> Operator<N> for Concatenate {
+    fn symbol(&self) -> &'static str {
+        "||"
+    }
+
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        crate::concatenate(left, right)
+    }
+
+    // strips the digits of `right` off the end of `result`
+    fn inverse(&self, result: &N, right: &N) -> Inverse<N> {
+        let left = match (result.checked_sub(right), right.shift()) {
+            (Some(rest), Some(shift)) if rest >= N::zero() => rest
+                .checked_div(&shift)
+                .filter(|left| left.checked_mul(&shift).as_ref() == Some(&rest)),
+            // 0 || right is the only way to get a result whose shift doesn't fit
+            (Some(rest), None) if rest.is_zero() && right >= &N::zero() => Some(N::zero()),
+            _ => None,
+        };
+        Inverse::Values(left.into_iter().collect())
+    }
+}
+
+impl<N: Number> Operator<N> for Subtract {
+    fn symbol(&self) -> &'static str {
+        "-"
+    }
+
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_sub(right)
+    }
+
+    fn inverse(&self, result: &N, right: &N) -> Inverse<N> {
+        Inverse::Values(result.checked_add(right).into_iter().collect())
+    }
+
+    fn keeps_non_negative(&self) -> bool {
+        false
+    }
+}
+
+// Integer division, rounding towards zero.
+impl<N: Number> Operator<N> for Divide {
+    fn symbol(&self) -> &'static str {
+        "/"
+    }
+
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_div(right)
+    }
+
+    // Every left in a window of `right` values rounds to the same result. As
+    // division rounds towards zero, left / -d == -(left / d), so a negative
+    // divisor has the same window as the positive one for the negated result.
+    fn inverse(&self, result: &N, right: &N) -> Inverse<N> {
+        if right.is_zero() {
+            return Inverse::Values(Vec::new());
+        }
+        if right < &N::zero() {
+            return match (N::zero().checked_sub(result), N::zero().checked_sub(right)) {
+                (Some(result), Some(right)) => self.inverse(&result, &right),
+                _ => Inverse::Values(Vec::new()),
+            };
+        }
+
+        let base = result.checked_mul(right);
+        let spread = right.clone() - N::one();
+        let (low, high) = match result.cmp(&N::zero()) {
+            std::cmp::Ordering::Greater => (base.clone(), base.and_then(|base| base.checked_add(&spread))),
+            std::cmp::Ordering::Less => (base.clone().and_then(|base| base.checked_sub(&spread)), base),
+            std::cmp::Ordering::Equal => (N::zero().checked_sub(&spread), Some(spread)),
+        };
+
+        match (low, high) {
+            (Some(low), Some(high)) => Inverse::Range(low, high),
+            _ => Inverse::Values(Vec::new()),
+        }
+    }
+}
+
+impl<N: Number> Operator<N> for Power {
+    fn symbol(&self) -> &'static str {
+        "^"
+    }
+
+    fn apply(&self, left: &N, right: &N) -> Option<N> {
+        right.to_usize().and_then(|exponent| num_traits::checked_pow(left.clone(), exponent))
+    }
+
+    // integer roots of `result`
+    fn inverse(&self, result: &N, right: &N) -> Inverse<N> {
+        let exponent = match right.to_u32() {
+            Some(exponent) => exponent,
+            None => return Inverse::Values(Vec::new()),
+        };
+
+        match exponent {
+            0 if result.is_one() => Inverse::Any,
+            0 => Inverse::Values(Vec::new()),
+            1 => Inverse::Values(vec![result.clone()]),
+            e if e % 2 == 0 && result < &N::zero() => Inverse::Values(Vec::new()),
+            e => {
+                let root = result.nth_root(e);
+                let mut roots: Vec<N> = match e % 2 {
+                    0 => vec![N::zero() - root.clone(), root],
+                    _ => vec![root],
+                };
+                roots.retain(|root| self.apply(root, right).as_ref() == Some(result));
+                roots.dedup();
+                Inverse::Values(roots)
+            },
+        }
+    }
+}
+
+// Every operator that can be picked for a run, by symbol.
+pub fn registry<N: Number>() -> [&'static dyn Operator<N>; 6] {
+    [&Add, &Multiply, &Concatenate, &Subtract, &Divide, &Power]
+}
+
+// The operators chosen for one run, in the order the solver tries them.
+pub struct Operators<N: Number> {
+    ops: Vec<&'static dyn Operator<N>>,
+}
+
+impl<N: Number> Operators<N> {
+    pub fn part_one() -> Self {
+        Operators { ops: vec![&Add, &Multiply] }
+    }
+
+    pub fn part_two() -> Self {
+        Operators { ops: vec![&Add, &Multiply, &Concatenate] }
+    }
+
+    // A comma separated list of symbols, like `+,*,||`.
+    pub fn parse(list: &str) -> Result<Self> {
+        let mut ops: Vec<&'static dyn Operator<N>> = Vec::new();
+        for symbol in list.split(',').map(str::trim) {
+            let op = match registry::<N>().into_iter().find(|op| op.symbol() == symbol) {
+                Some(op) => op,
+                None => {
+                    let known: Vec<&str> = registry::<N>().iter().map(|op| op.symbol()).collect();
+                    bail!("Unknown operator '{}', expected one of {}", symbol, known.join(" "));
+                },
+            };
+            if ops.iter().any(|chosen| chosen.symbol() == symbol) {
+                bail!("The operator '{}' is listed more than once", symbol);
+            }
+            ops.push(op);
+        }
+
+        Ok(Operators { ops })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'static dyn Operator<N>> + '_ {
+        self.ops.iter().copied()
+    }
+
+    pub fn keeps_non_negative(&self) -> bool {
+        self.ops.iter().all(|op| op.keeps_non_negative())
+    }
+
+    pub fn grows_with_left(&self) -> bool {
+        self.ops.iter().all(|op| op.grows_with_left())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(inverse: Inverse<i64>) -> Option<Vec<i64>> {
+        match inverse {
+            Inverse::Values(values) => Some(values),
+            Inverse::Range(low, high) => Some((low..=high).collect()),
+            Inverse::Any => None,
+        }
+    }
+
+    #[test]
+    fn test_shift() {
+        assert_eq!(0i64.shift(), Some(10));
+        assert_eq!(7i64.shift(), Some(10));
+        assert_eq!(10i64.shift(), Some(100));
+        assert_eq!(999i64.shift(), Some(1000));
+        assert_eq!((-5i64).shift(), None);
+        assert_eq!(i64::MAX.shift(), None);
+        assert_eq!((i64::MAX as i128).shift(), Some(10i128.pow(19)));
+        assert_eq!(BigInt::from(0).shift(), Some(BigInt::from(10)));
+        assert_eq!(BigInt::from(i64::MAX).shift(), Some(BigInt::from(10).pow(19)));
+    }
+
+    #[test]
+    fn test_inverses() {
+        assert_eq!(values(Add.inverse(&10, &3)), Some(vec![7]));
+        assert_eq!(values(Multiply.inverse(&12, &4)), Some(vec![3]));
+        assert_eq!(values(Multiply.inverse(&13, &4)), Some(vec![]));
+        assert_eq!(values(Multiply.inverse(&0, &0)), None);
+        assert_eq!(values(Concatenate.inverse(&156, &6)), Some(vec![15]));
+        assert_eq!(values(Concatenate.inverse(&156, &7)), Some(vec![]));
+        assert_eq!(values(Concatenate.inverse(&50, &0)), Some(vec![5]));
+        assert_eq!(values(Subtract.inverse(&-2, &5)), Some(vec![3]));
+        assert_eq!(values(Divide.inverse(&3, &4)), Some(vec![12, 13, 14, 15]));
+        assert_eq!(values(Divide.inverse(&0, &3)), Some(vec![-2, -1, 0, 1, 2]));
+        assert_eq!(values(Divide.inverse(&5, &0)), Some(vec![]));
+        assert_eq!(values(Divide.inverse(&-2, &-2)), Some(vec![4, 5]));
+        assert_eq!(values(Divide.inverse(&2, &-3)), Some(vec![-8, -7, -6]));
+        assert!(matches!(Divide.inverse(&5i64, &1_000_000_000_000), Inverse::Range(5_000_000_000_000, 5_999_999_999_999)));
+        assert_eq!(values(Power.inverse(&81, &4)), Some(vec![-3, 3]));
+        assert_eq!(values(Power.inverse(&-27, &3)), Some(vec![-3]));
+        assert_eq!(values(Power.inverse(&80, &4)), Some(vec![]));
+        assert_eq!(values(Power.inverse(&1, &0)), None);
+
+        // nothing overflows or panics on the way back
+        assert_eq!(values(Multiply.inverse(&i64::MIN, &-1)), Some(vec![]));
+        assert_eq!(values(Add.inverse(&i64::MIN, &1)), Some(vec![]));
+        assert_eq!(values(Subtract.inverse(&i64::MAX, &1)), Some(vec![]));
+        assert_eq!(values(Divide.inverse(&i64::MAX, &2)), Some(vec![]));
+        assert_eq!(values(Divide.inverse(&i64::MIN, &-1)), Some(vec![]));
+        assert_eq!(values(Concatenate.inverse(&i64::MAX, &i64::MAX)), Some(vec![0]));
+        assert_eq!(values(Power.inverse(&i64::MAX, &2)), Some(vec![]));
+
+        // every inverse really undoes the operator
+        for op in registry::<i64>() {
+            for (result, right) in [(156, 6), (81, 4), (0, 3), (7, 1), (70, 0), (i64::MAX, 7), (-2, -2), (2, -3), (0, -4)] {
+                if let Some(lefts) = values(op.inverse(&result, &right)) {
+                    assert!(lefts.iter().all(|left| op.apply(left, &right) == Some(result)), "{}", op.symbol());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_wide_inverses() {
+        let big = BigInt::from(10).pow(30);
+        match Power.inverse(&big, &BigInt::from(3)) {
+            Inverse::Values(roots) => assert_eq!(roots, vec![BigInt::from(10).pow(10)]),
+            _ => panic!("a cube has one cube root"),
+        }
+        match Concatenate.inverse(&(i64::MAX as i128 * 10), &0) {
+            Inverse::Values(lefts) => assert_eq!(lefts, vec![i64::MAX as i128]),
+            _ => panic!("concatenation always fixes the left operand"),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let ops = Operators::<i64>::parse("+, *, ||").unwrap();
+        assert_eq!(ops.iter().map(|op| op.symbol()).collect::<Vec<_>>(), vec!["+", "*", "||"]);
+        assert!(ops.keeps_non_negative());
+        assert!(!Operators::<i64>::parse("+,-").unwrap().keeps_non_negative());
+        assert_eq!(Operators::<i64>::parse("+,%").err().unwrap().to_string(), "Unknown operator '%', expected one of + * || - / ^");
+        assert!(Operators::<i64>::parse("+,+").is_err());
+    }
+}
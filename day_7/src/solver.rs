@@ -0,0 +1,375 @@
+use crate::operators::{Inverse, Number, Operator, Operators};
+use std::collections::BTreeSet;
+
+// Works an equation backwards from its test value instead of trying every
+// operator assignment. The last number was combined with everything before it
+// by one of the operators, so each operator's inverse says what the rest of the
+// equation would have to come to. Any operator that can't be undone prunes
+// that whole branch, and so does one whose inverse doesn't fit in the number
+// type.
+
+pub type Witness<N> = Vec<&'static dyn Operator<N>>;
+
+struct Search<'a, N: Number> {
+    ops: &'a Operators<N>,
+    // all operands and operators are non-negative, so negative targets are dead ends
+    prune_negative: bool,
+    // operators chosen so far, from the right
+    stack: Witness<N>,
+    // sees each witness and returns true to stop the search
+    visit: &'a mut dyn FnMut(&[&'static dyn Operator<N>]) -> bool,
+    // when only counting, the witnesses found so far, so whole branches can be
+    // added up without listing them
+    tally: Option<usize>,
+}
+
+impl<N: Number> Search<'_, N> {
+    fn run(&mut self, target: &N, nums: &[N]) -> bool {
+        let (last, rest) = match nums.split_last() {
+            Some(split) => split,
+            None => return false,
+        };
+        if rest.is_empty() {
+            return target == last && self.report();
+        }
+        if self.prune_negative && target < &N::zero() {
+            return false;
+        }
+
+        for op in self.ops.iter() {
+            self.stack.push(op);
+            let stop = match op.inverse(target, last) {
+                Inverse::Values(lefts) => lefts.iter().any(|left| self.run(left, rest)),
+                Inverse::Range(low, high) => self.run_range(low, high, rest),
+                Inverse::Any => self.every_assignment(rest),
+            };
+            self.stack.pop();
+            if stop {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // Tries each left operand in a window, after narrowing it to the values
+    // the rest of the numbers could come to at all. A window wider than the
+    // number of ways of evaluating them is matched against the values they do
+    // come to instead, so a trillion wide window costs nothing when there is
+    // a single number left, and - (which leaves the window unbounded) can't
+    // turn it into a walk over every value.
+    fn run_range(&mut self, low: N, high: N, nums: &[N]) -> bool {
+        let (low, high) = match self.reach(nums) {
+            Some((reach_low, reach_high)) => (low.max(reach_low), reach_high.map_or(high.clone(), |reach| high.min(reach))),
+            None if self.bounded() => return false,
+            None => (low, high),
+        };
+
+        let slots = u32::try_from(nums.len() - 1).unwrap_or(u32::MAX);
+        let ways = self.ops.iter().count().saturating_pow(slots);
+        match high.checked_sub(&low).and_then(|width| width.to_usize()) {
+            Some(width) if width < ways => {
+                let mut left = low;
+                while left <= high {
+                    if self.run(&left, nums) {
+                        return true;
+                    }
+                    left = left + N::one();
+                }
+                false
+            },
+            _ => self
+                .values(nums)
+                .into_iter()
+                .filter(|value| &low <= value && value <= &high)
+                .any(|value| self.run(&value, nums)),
+        }
+    }
+
+    fn report(&mut self) -> bool {
+        if let Some(tally) = self.tally.as_mut() {
+            *tally += 1;
+            return false;
+        }
+        let witness: Witness<N> = self.stack.iter().rev().copied().collect();
+        (self.visit)(&witness)
+    }
+
+    // When any value works for the numbers before the last operator, so does
+    // every way of writing them that can be evaluated without overflowing.
+    fn every_assignment(&mut self, nums: &[N]) -> bool {
+        if let Some(tally) = self.tally {
+            self.tally = Some(tally.saturating_add(self.ways(&nums[0], &nums[1..])));
+            return false;
+        }
+
+        self.forward(&nums[0], &nums[1..], &mut Vec::new())
+    }
+
+    // How many ways of carrying on from `value` through `nums` can be
+    // evaluated. A branch stops as soon as it overflows, and any branch that
+    // can't overflow from there on has all its ways counted in one go.
+    fn ways(&self, value: &N, nums: &[N]) -> usize {
+        if self.never_overflows(value, nums) {
+            let slots = u32::try_from(nums.len()).unwrap_or(u32::MAX);
+            return self.ops.iter().count().saturating_pow(slots);
+        }
+
+        let mut ways: usize = 0;
+        for op in self.ops.iter() {
+            if let Some(value) = op.apply(value, &nums[0]) {
+                ways = ways.saturating_add(self.ways(&value, &nums[1..]));
+            }
+        }
+
+        ways
+    }
+
+    // Nothing is negative and every operator grows with its left operand, so
+    // the smallest and largest values a list of numbers can be evaluated to
+    // come from taking the smallest or largest result at each step.
+    fn bounded(&self) -> bool {
+        self.prune_negative && self.ops.grows_with_left()
+    }
+
+    // The largest value carrying on from `value` through `nums` can reach, or
+    // None if some way of getting there overflows.
+    fn highest(&self, value: &N, nums: &[N]) -> Option<N> {
+        let mut high = value.clone();
+        for num in nums {
+            let mut next = None;
+            for op in self.ops.iter() {
+                next = next.max(Some(op.apply(&high, num)?));
+            }
+            high = next?;
+        }
+
+        Some(high)
+    }
+
+    // Every way of carrying on from `value` through `nums` can be evaluated if
+    // even the largest value any of them reaches fits.
+    fn never_overflows(&self, value: &N, nums: &[N]) -> bool {
+        nums.is_empty() || (self.bounded() && self.highest(value, nums).is_some())
+    }
+
+    // The smallest and largest values `nums` can be evaluated to, the largest
+    // being None when some way overflows. None when that can't be worked out,
+    // or when no way of evaluating them fits at all.
+    fn reach(&self, nums: &[N]) -> Option<(N, Option<N>)> {
+        if !self.bounded() {
+            return None;
+        }
+
+        let mut low = nums[0].clone();
+        for num in &nums[1..] {
+            // a result that overflows from the smallest value does from every other
+            low = self.ops.iter().filter_map(|op| op.apply(&low, num)).min()?;
+        }
+
+        Some((low, self.highest(&nums[0], &nums[1..])))
+    }
+
+    // Every distinct value the numbers can be evaluated to without overflowing.
+    fn values(&self, nums: &[N]) -> BTreeSet<N> {
+        let mut values = BTreeSet::from([nums[0].clone()]);
+        for num in &nums[1..] {
+            values = values
+                .iter()
+                .flat_map(|value| self.ops.iter().filter_map(move |op| op.apply(value, num)))
+                .collect();
+        }
+
+        values
+    }
+
+    // Evaluates the numbers before the last operator left to right, reporting
+    // each assignment of operators that doesn't overflow.
+    fn forward(&mut self, value: &N, nums: &[N], prefix: &mut Witness<N>) -> bool {
+        let (next, rest) = match nums.split_first() {
+            Some(split) => split,
+            None => {
+                let base = self.stack.len();
+                self.stack.extend(prefix.iter().rev());
+                let stop = self.report();
+                self.stack.truncate(base);
+                return stop;
+            },
+        };
+
+        for op in self.ops.iter() {
+            if let Some(value) = op.apply(value, next) {
+                prefix.push(op);
+                let stop = self.forward(&value, rest, prefix);
+                prefix.pop();
+                if stop {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+fn searcher<'a, N: Number>(nums: &[N], ops: &'a Operators<N>, visit: &'a mut dyn FnMut(&[&'static dyn Operator<N>]) -> bool) -> Search<'a, N> {
+    let prune_negative = ops.keeps_non_negative() && nums.iter().all(|num| num >= &N::zero());
+    Search { ops, prune_negative, stack: Vec::new(), visit, tally: None }
+}
+
+fn search<N: Number>(target: &N, nums: &[N], ops: &Operators<N>, visit: &mut dyn FnMut(&[&'static dyn Operator<N>]) -> bool) {
+    searcher(nums, ops, visit).run(target, nums);
+}
+
+pub fn solvable<N: Number>(target: &N, nums: &[N], ops: &Operators<N>) -> bool {
+    solve(target, nums, ops).is_some()
+}
+
+// The first operator sequence found that makes the equation true.
+pub fn solve<N: Number>(target: &N, nums: &[N], ops: &Operators<N>) -> Option<Witness<N>> {
+    let mut witness = None;
+    search(target, nums, ops, &mut |found| {
+        witness = Some(found.to_vec());
+        true
+    });
+
+    witness
+}
+
+// Every operator sequence that makes the equation true.
+pub fn solutions<N: Number>(target: &N, nums: &[N], ops: &Operators<N>) -> Vec<Witness<N>> {
+    let mut all = Vec::new();
+    search(target, nums, ops, &mut |found| {
+        all.push(found.to_vec());
+        false
+    });
+
+    all
+}
+
+// Saturates at usize::MAX rather than overflowing.
+pub fn count<N: Number>(target: &N, nums: &[N], ops: &Operators<N>) -> usize {
+    let mut ignore = |_: &[&'static dyn Operator<N>]| false;
+    let mut search = searcher(nums, ops, &mut ignore);
+    search.tally = Some(0);
+    search.run(target, nums);
+
+    search.tally.unwrap_or(0)
+}
+
+// Writes an equation out like the puzzle text does, e.g. `81 + 40 * 27`.
+pub fn expression<N: Number>(nums: &[N], ops: &[&dyn Operator<N>]) -> String {
+    let mut output = nums.first().map_or(String::new(), |num| num.to_string());
+    for (op, num) in ops.iter().zip(nums.iter().skip(1)) {
+        output.push_str(&format!(" {} {}", op.symbol(), num));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigInt;
+
+    fn symbols(witness: &[&dyn Operator<i64>]) -> Vec<&'static str> {
+        witness.iter().map(|op| op.symbol()).collect()
+    }
+
+    #[test]
+    fn test_solvable() {
+        let (one, two) = (Operators::<i64>::part_one(), Operators::<i64>::part_two());
+        assert!(solvable(&190, &[10, 19], &one));
+        assert!(solvable(&3267, &[81, 40, 27], &one));
+        assert!(solvable(&292, &[11, 6, 16, 20], &one));
+        assert!(!solvable(&156, &[15, 6], &one));
+        assert!(solvable(&156, &[15, 6], &two));
+        assert!(solvable(&7290, &[6, 8, 6, 15], &two));
+        assert!(solvable(&192, &[17, 8, 14], &two));
+        assert!(!solvable(&21037, &[9, 7, 18, 13], &two));
+        assert!(!solvable(&161011, &[16, 10, 13], &two));
+    }
+
+    #[test]
+    fn test_witnesses() {
+        let (one, two) = (Operators::<i64>::part_one(), Operators::<i64>::part_two());
+        assert_eq!(symbols(&solve(&3267, &[81, 40, 27], &one).unwrap()), vec!["*", "+"]);
+        let all: Vec<Vec<&str>> = solutions(&3267, &[81, 40, 27], &one).iter().map(|w| symbols(w)).collect();
+        assert_eq!(all, vec![vec!["*", "+"], vec!["+", "*"]]);
+        assert_eq!(count(&3267, &[81, 40, 27], &one), 2);
+        assert!(solve(&83, &[17, 5], &two).is_none());
+        assert_eq!(count(&83, &[17, 5], &two), 0);
+
+        let witness = solve(&7290, &[6, 8, 6, 15], &two).unwrap();
+        assert_eq!(expression(&[6, 8, 6, 15], &witness), "6 * 8 || 6 * 15");
+
+        // everything before a trailing * 0 can be anything
+        assert_eq!(count(&0, &[1, 2, 0], &one), 2);
+        assert_eq!(count(&0, &[1, 2, 0], &two), 3);
+        let long: Vec<i64> = (1..=20).chain([0]).collect();
+        assert_eq!(count(&0, &long, &one), 2usize.pow(19));
+        let short: Vec<i64> = (1..=12).chain([0]).collect();
+        assert_eq!(count(&0, &short, &two), 3usize.pow(11));
+    }
+
+    #[test]
+    fn test_other_operators() {
+        let ops = Operators::<i64>::parse("+,-").unwrap();
+        assert_eq!(expression(&[5, 9, 2], &solve(&-2, &[5, 9, 2], &ops).unwrap()), "5 - 9 + 2");
+
+        let ops = Operators::<i64>::parse("+,/,^").unwrap();
+        // the window of values that divide down to 5 is a trillion wide
+        assert!(solvable(&5, &[5_000_000_000_000, 1_000_000_000_000], &ops));
+        assert!(!solvable(&5, &[4_000_000_000_000, 1_000_000_000_000], &ops));
+        let signed = Operators::<i64>::parse("-,/").unwrap();
+        assert!(solvable(&0, &[3, 4, 5], &signed));
+        assert!(solvable(&-2, &[4, -2], &signed));
+        assert!(!solvable(&5, &[4_000_000_000, 1_000_000_000], &signed));
+        assert!(solvable(&5, &[5_000_000_000_000, 1_000_000_000_000], &signed));
+        assert!(solvable(&-2, &[7, 3_000_000_000_000, 1_000_000_000_000], &signed));
+        assert_eq!(count(&-2, &[7, 3_000_000_000_000, 1_000_000_000_000], &signed), 1);
+        // the rest of the numbers reach a few trillion wide span
+        let scaled = Operators::<i64>::parse("+,*,/").unwrap();
+        assert!(!solvable(&5, &[3_000_000_000_000, 2, 1_000_000_000_000], &scaled));
+        assert!(solvable(&6, &[3_000_000_000_000, 2, 1_000_000_000_000], &scaled));
+        assert!(solvable(&2, &[-7, -3], &signed));
+        assert_eq!(expression(&[7, 2, 3], &solve(&27, &[7, 2, 3], &ops).unwrap()), "7 / 2 ^ 3");
+        assert_eq!(count(&4, &[9, 2, 1], &ops), 2);
+    }
+
+    #[test]
+    fn test_solvable_edges() {
+        let (one, two) = (Operators::<i64>::part_one(), Operators::<i64>::part_two());
+        assert!(solvable(&5, &[5], &one));
+        assert!(!solvable(&5, &[], &one));
+        assert!(solvable(&0, &[7, 3, 0], &one));
+        assert!(solvable(&70, &[7, 0], &two));
+        assert!(!solvable(&3, &[5, 2], &one));
+        assert!(solvable(&0, &[0, 0], &two));
+        assert!(solvable(&50, &[5, 0], &two));
+        assert_eq!(count(&500, &[5, 0, 0], &two), 1);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let two = Operators::<i64>::part_two();
+        // the test value fits but every way of getting there overflows
+        assert!(!solvable(&i64::MAX, &[i64::MAX, 2, 1], &two));
+        assert!(solvable(&i64::MAX, &[i64::MAX / 2, 2, 1], &two));
+        // anything * 0 is 0, but only if the anything can be evaluated
+        assert!(!solvable(&0, &[i64::MAX, 2, 0], &two));
+        assert_eq!(count(&0, &[i64::MAX, 2, 0], &two), 0);
+        assert!(!solvable(&1, &[i64::MAX, 2, 0], &Operators::<i64>::parse("+,*,^").unwrap()));
+        assert_eq!(count(&0, &[i64::MAX, 1, 0], &two), 1);
+        assert!(!solvable(&i64::MIN, &[i64::MIN, 1], &Operators::<i64>::parse("+,-").unwrap()));
+
+        let wide = Operators::<i128>::part_two();
+        assert!(solvable(&(i64::MAX as i128 * 10), &[i64::MAX as i128, 0], &wide));
+        assert!(solvable(&(i64::MAX as i128 * 2 + 1), &[i64::MAX as i128, 2, 1], &wide));
+
+        let big = Operators::<BigInt>::part_two();
+        let huge: BigInt = "12345678901234567890198765432109876543210".parse().unwrap();
+        let parts = ["123456789012345678901", "98765432109876543210"].map(|part| part.parse::<BigInt>().unwrap());
+        assert_eq!(count(&huge, &parts, &big), 1);
+    }
+}